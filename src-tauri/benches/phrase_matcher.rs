@@ -0,0 +1,100 @@
+// cargo bench --bench phrase_matcher
+// the phrase trie against the join-and-lookup loop annotate_phrase used before it, and annotate_phrase itself.
+use criterion::{ black_box, criterion_group, criterion_main, Criterion };
+use ebook_wordwise::shenhe::annotation::annotate_phrase;
+use ebook_wordwise::shenhe::occurrence::{ DensityLimit, OccurrenceLimit };
+use ebook_wordwise::shenhe::phrase::{ PhraseMatcher, Tokens, DEFAULT_MAX_PHRASE_LENGTH };
+use ebook_wordwise::shenhe::segment::Segmenter;
+use ebook_wordwise::shenhe::types::{ Annotator, ChunkParameter, Clean, Cleaner, DictRecord, Difficulty };
+use std::collections::{ HashMap, HashSet };
+
+const SENTENCE: &str =
+    "The business of eating being concluded, and no one uttering a word of sociable conversation, I approached a window to examine the weather. I think this is in someone's pocket, but I'm not advancement. ";
+
+fn record(word: &str) -> DictRecord {
+    DictRecord {
+        word: word.to_string(),
+        short_def: format!("def of {}", word),
+        hint_lvl: 1,
+        ..Default::default()
+    }
+}
+
+// about the size of the english dictionary, with the words and phrases of the sentence in it.
+fn dictionary() -> HashMap<String, DictRecord> {
+    let mut dict = HashMap::new();
+    for n in 0..100_000 {
+        let word = format!("headword{}", n);
+        dict.insert(word.clone(), record(&word));
+    }
+    for n in 0..20_000 {
+        let phrase = format!("the phrase{} of it", n);
+        dict.insert(phrase.clone(), record(&phrase));
+    }
+    let words = SENTENCE.split_whitespace().map(|word| Cleaner::clean_word(word, false).0);
+    for word in words.chain(["in someone's pocket".to_string(), "a word of".to_string()]) {
+        dict.insert(word.clone(), record(&word));
+    }
+    dict
+}
+
+// the loop annotate_phrase used before the phrase trie
+fn legacy_longest_match(words: &[&str], i: usize, dict: &HashMap<String, DictRecord>) -> usize {
+    let mut longest_length = 0;
+    for j in i + 1..=words.len() {
+        if j - i > DEFAULT_MAX_PHRASE_LENGTH {
+            break;
+        }
+        let phrase: String = words[i..j].join(" ");
+        let (cleaned_phrase, _, _) = Cleaner::clean_word(&phrase, false);
+        if dict.contains_key(&cleaned_phrase) {
+            longest_length = j - i;
+        }
+    }
+    longest_length
+}
+
+fn bench_phrase_matcher(c: &mut Criterion) {
+    let dict = dictionary();
+    let lemma = HashMap::new();
+    let text = SENTENCE.repeat(100);
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let tokens = Tokens::new(&words);
+    let matcher = PhraseMatcher::new(&dict, DEFAULT_MAX_PHRASE_LENGTH);
+
+    c.bench_function("join and lookup", |b| {
+        b.iter(|| (0..words.len()).map(|i| legacy_longest_match(black_box(&words), i, &dict)).sum::<usize>())
+    });
+    c.bench_function("phrase trie", |b| {
+        b.iter(|| {
+            (0..words.len())
+                .filter_map(|i| matcher.longest_match(black_box(&tokens), i, &lemma))
+                .map(|(_, length)| length)
+                .sum::<usize>()
+        })
+    });
+    c.bench_function("build the phrase trie", |b| {
+        b.iter(|| PhraseMatcher::new(black_box(&dict), DEFAULT_MAX_PHRASE_LENGTH))
+    });
+
+    let segmenter = Segmenter::new("en", &dict);
+    let annotator = Annotator::RubyAnnotator(Difficulty::HintLevel(5), false);
+    let excluded = HashSet::new();
+    let param = ChunkParameter {
+        dict: &dict,
+        lemma: &lemma,
+        matcher: &matcher,
+        segmenter: &segmenter,
+        excluded: &excluded,
+        book_language: "en",
+        def_length: 1,
+        occurrence_limit: OccurrenceLimit::default(),
+        density_limit: DensityLimit::default(),
+        fade: None,
+        annotator: &annotator,
+    };
+    c.bench_function("annotate_phrase", |b| b.iter(|| annotate_phrase(black_box(SENTENCE), &param)));
+}
+
+criterion_group!(benches, bench_phrase_matcher);
+criterion_main!(benches);
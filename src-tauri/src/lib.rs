@@ -0,0 +1,2 @@
+// the annotation engine, the app in main.rs and the benches use it.
+pub mod shenhe;
@@ -1,56 +1,113 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
-use serde::Deserialize;
-
-mod shenhe;
+use ebook_wordwise::shenhe;
 use shenhe::{
-    annotation::{ load_dict, load_lemma },
-    cmd::{ ebook_convert_exists, run_command },
+    annotation::load_dict,
+    batch::{ run_batch, skip_outputs, BatchBookEvent, BatchRequest },
+    calibre::{ ebook_convert, forget as forget_ebook_convert, relocate, CalibreReport },
+    cli::{ parse_args, StartupOptions },
+    cmd::run_command,
     html::{ self, process_text },
+    history::{ History, HistoryEntry, HistoryFilter, DATA_DIR },
+    job::{ CancelToken, JobResult, Jobs },
+    names::{ detect_names, exclusion_list },
+    occurrence::resolve_occurrences,
+    output::{ check_not_source, check_writable, destination, output_folder, output_name, Artifact },
+    phrase::PhraseMatcher,
+    placement::{ PlacementAnswer, PlacementQuestion, PlacementResult, PlacementTest },
+    presets::{ Preset, Presets },
+    progression::{ load_series, record_series_book, SeriesProgress },
+    load_dictionaries,
     process,
-    types::{ Annotator, ChunkParameter, Payload, ProgressReporter, WorkMesg, APP_DATA_DIR },
+    report::{ analyze, export_report, BookReport },
+    segment::Segmenter,
+    settings::{ load_settings, save_settings as store_settings, AppSetting, CONFIG_DIR },
+    types::{
+        Annotator,
+        ChunkParameter,
+        Payload,
+        ProgressReporter,
+        WorkMesg,
+        APP_DATA_DIR,
+    },
+    workspace::{ move_file, sweep, workspace_root, Workspace, CACHE_DIR, ORPHAN_AGE },
+};
+use std::{
+    collections::HashMap,
+    error::Error,
+    panic::{ self, AssertUnwindSafe },
+    path::Path,
+    sync::Mutex,
+    time::SystemTime,
 };
-use std::{ error::Error, path::Path };
-use tauri::api::path::resource_dir;
-use tauri::{ Builder, Manager, Runtime };
+use tauri::api::path::{ app_cache_dir, app_config_dir, app_data_dir, resource_dir };
+use tauri::{ Builder, Manager, Runtime, State };
 use uuid::Uuid;
 const RESORUCE_FOLDER: &'static str = "resources";
 
 fn progress_fn<R: Runtime>(progress: f32, tauri_window: &tauri::Window<R>) {
-    let percent = (0.2 + (0.9 - 0.2) * progress) * 100.0; // map to [20%, 90%]
-    tauri_window.emit("event-progress", percent).unwrap();
+    // the reporter maps each step into its part of the job
+    tauri_window.emit("event-progress", progress * 100.0).unwrap();
 }
 
+fn quiet_progress_fn<R: Runtime>(_progress: f32, _tauri_window: &tauri::Window<R>) {}
+
+// look for calibre again, the report tells where it was found or why it can't be used.
 #[tauri::command]
-fn check_ebook_convert() -> Result<bool, String> {
-    let exists = ebook_convert_exists();
-    Ok(exists)
+fn check_ebook_convert() -> Result<CalibreReport, String> {
+    let (settings, _) = load_settings()?;
+    Ok(relocate(&settings.ebook_convert))
 }
 
 #[tauri::command]
-fn preview(payload: Payload, original: &str) -> String {
+fn preview(payload: Payload, original: &str) -> Result<String, String> {
     //println!("payload: {:?}", payload);
-    let lemma = load_lemma().unwrap();
-    let dict = load_dict(payload.language.as_str()).unwrap();
-    let annotator = match payload.wordwise_style {
-        0 => Annotator::InlineAnnotator(payload.hint_level, payload.show_phoneme),
-        1 => Annotator::RubyAnnotator(payload.hint_level, payload.show_phoneme),
-        2 => Annotator::ColorAnnotator("red", payload.hint_level, payload.show_phoneme),
-        _ => Annotator::InlineAnnotator(payload.hint_level, payload.show_phoneme),
+    // the same dictionaries as the book gets, a missing one fails the preview like it fails the job
+    let (lemma, dict) = load_dictionaries(&payload)?;
+    let annotator = match payload.style() {
+        0 => Annotator::InlineAnnotator(payload.difficulty(), payload.show_phoneme),
+        1 => Annotator::RubyAnnotator(payload.difficulty(), payload.show_phoneme),
+        2 =>
+            Annotator::ColorAnnotator(
+                &payload.annotation_color,
+                payload.difficulty(),
+                payload.show_phoneme
+            ),
+        _ => Annotator::InlineAnnotator(payload.difficulty(), payload.show_phoneme),
     };
 
     let def_len = match payload.allow_long {
         false => 1,
         true => 2,
     };
+    let matcher = PhraseMatcher::new(&dict, payload.max_phrase_length);
+    let segmenter = Segmenter::new(payload.book_language.as_str(), &dict);
+    let excluded = exclusion_list(&[], &payload.excluded_names, &payload.included_names);
+    // the preview shows the start of the first book
+    let fade = payload.fade(0);
     let param: ChunkParameter = ChunkParameter {
         dict: &dict,
         lemma: &lemma,
+        matcher: &matcher,
+        segmenter: &segmenter,
+        excluded: &excluded,
+        book_language: payload.book_language.as_str(),
         def_length: def_len,
+        occurrence_limit: payload.occurrence_limit(),
+        density_limit: payload.density_limit(),
+        fade,
         annotator: &annotator,
     };
 
-    process_text(original, &param, html::process_text_fn)
+    let mut preview = process_text(original, &param, html::process_text_fn);
+    resolve_occurrences(
+        std::slice::from_mut(&mut preview),
+        param.occurrence_limit,
+        param.density_limit,
+        param.fade,
+        &mut HashMap::new()
+    );
+    Ok(preview)
 }
 
 #[tauri::command]
@@ -74,26 +131,18 @@ async fn open_directory<R: Runtime>(app: tauri::AppHandle<R>) -> Result<(), Stri
     Ok(())
 }
 
-#[tauri::command]
-async fn start_job<R: Runtime>(
-    window: tauri::Window<R>,
-    payload: Payload
+// convert the book to html with calibre in the workspace, returns the folder the html is extracted to.
+fn convert_to_html<R: Runtime>(
+    window: &tauri::Window<R>,
+    reporter: &ProgressReporter<R>,
+    book: &str,
+    workspace: &Workspace
 ) -> Result<String, String> {
-    window.emit("event-progress", 0.0).map_err(|e| e.to_string())?;
-    const EBOOK_CONVERT: &'static str = "ebook-convert";
-    let book = (&payload).book.as_str();
-    if book.is_empty() {
-        return Err("Empty book path, please select a book.".to_string());
-    }
-
-    let book_path = Path::new(book).parent().unwrap().to_str().unwrap();
     let book_name_without_ext = Path::new(book).file_stem().unwrap().to_str().unwrap();
-
-    let uuid = Uuid::new_v4().to_string();
-    let book_out_dir: String = format!("{}/{}/", book_path, uuid);
-    //std::fs::create_dir_all(&book_out_dir).map_err(|e| e.to_string())?;
-    let book_dump = format!("{}/{}.htmlz", book_path, book_name_without_ext);
-    let reporter = ProgressReporter::new(&window, progress_fn);
+    let book_dump = workspace.join(&format!("{}.htmlz", book_name_without_ext));
+    let book_dump = book_dump.to_str().unwrap();
+    let book_out_dir = workspace.join("html");
+    let book_out_dir = book_out_dir.to_str().unwrap();
 
     window
         .emit(
@@ -105,32 +154,239 @@ async fn start_job<R: Runtime>(
         )
         .map_err(|e| e.to_string())?;
 
-    run_command(EBOOK_CONVERT, Some(&reporter), &[book, book_dump.as_str()])?;
-    window.emit("event-progress", 10.0).map_err(|e| e.to_string())?;
-    run_command(EBOOK_CONVERT, Some(&reporter), &[book_dump.as_str(), (&book_out_dir).as_str()])?;
-    window.emit("event-progress", 20.0).map_err(|e| e.to_string())?;
+    let ebook_convert = ebook_convert()?;
+    // calibre's own progress fills 0 to 10% and 10 to 20%
+    run_command(&ebook_convert, Some(&reporter.within(0.0, 0.1)), &[book, book_dump])?;
+    run_command(&ebook_convert, Some(&reporter.within(0.1, 0.2)), &[book_dump, book_out_dir])?;
+    reporter.report(0.2);
 
-    let html_file = format!("{}/index1.html", book_out_dir);
-    let artifact_file = format!(
-        "{}/{}-wordwise.{}",
-        book_path,
-        book_name_without_ext,
-        (&payload).format
+    Ok(book_out_dir.to_string())
+}
+
+// starts annotating the book and returns the job's id right away, "event-job" tells how the job ended.
+#[tauri::command]
+fn start_job<R: Runtime>(
+    window: tauri::Window<R>,
+    jobs: State<'_, Jobs>,
+    payload: Payload,
+    preset: Option<String>
+) -> Result<String, String> {
+    if payload.book.is_empty() {
+        return Err("Empty book path, please select a book.".to_string());
+    }
+    let payload = with_preset(payload, preset)?;
+
+    Ok(spawn_job(window, jobs.inner().clone(), payload))
+}
+
+// the preset's options replace the payload's, for the same book.
+fn with_preset(payload: Payload, preset: Option<String>) -> Result<Payload, String> {
+    match preset {
+        Some(name) => Ok(Presets::default().get(&name)?.apply(&payload.book)),
+        None => Ok(payload),
+    }
+}
+
+fn spawn_job<R: Runtime>(window: tauri::Window<R>, jobs: Jobs, payload: Payload) -> String {
+    spawn(window, jobs, move |window, reporter, job_id, token| {
+        run_recorded_job(window, reporter, &payload, job_id, token).map(|artifact| artifact.message())
+    })
+}
+
+// run `work` on its own thread as a job cancel_job can stop, "event-job" tells how it ended.
+fn spawn<R, F>(window: tauri::Window<R>, jobs: Jobs, work: F) -> String
+    where
+        R: Runtime,
+        F: FnOnce(&tauri::Window<R>, &ProgressReporter<R>, &str, &CancelToken) -> Result<String, String> +
+            Send +
+            'static
+{
+    let (job_id, token) = jobs.start();
+    let id = job_id.clone();
+    std::thread::spawn(move || {
+        let reporter = ProgressReporter::new(&window, progress_fn).with_cancel(token.clone()).with_log();
+        let outcome = work(&window, &reporter, &id, &token);
+        jobs.finish(&id);
+        window.emit("event-job", JobResult::new(&id, outcome, &token)).ok();
+    });
+    job_id
+}
+
+// annotates every book of the request with the same settings and returns the batch's id right away.
+// "event-batch-book" follows the books and "event-batch" sums the batch up, cancel_job stops it.
+#[tauri::command]
+fn start_batch<R: Runtime>(
+    window: tauri::Window<R>,
+    jobs: State<'_, Jobs>,
+    payload: Payload,
+    request: BatchRequest,
+    preset: Option<String>
+) -> Result<String, String> {
+    let payload = with_preset(payload, preset)?;
+    let default_dir = load_settings()
+        .map(|(settings, _)| settings.output_dir)
+        .unwrap_or_default();
+    let mut books = skip_outputs(request.collect_books()?, |book| {
+        let payload = Payload { book: book.to_string_lossy().into_owned(), ..payload.clone() };
+        output_name(&payload)
+            .ok()
+            .map(|name| output_folder(&payload, &default_dir).join(name))
+    });
+    if books.is_empty() {
+        return Err("No book found, please select books or folders with books.".to_string());
+    }
+    let series = payload.series().is_some();
+    if series {
+        // the books of a series are annotated in reading order, the ones given by name too
+        books.sort();
+    }
+
+    let jobs = jobs.inner().clone();
+    let (batch_id, token) = jobs.start();
+    let id = batch_id.clone();
+    std::thread::spawn(move || {
+        let convert = |book: &Path| {
+            let payload = Payload { book: book.to_string_lossy().into_owned(), ..payload.clone() };
+            // the books run side by side, the batch's progress is the books finished
+            let reporter = ProgressReporter::new(&window, quiet_progress_fn).with_cancel(token.clone());
+            run_recorded_job(&window, &reporter, &payload, &Uuid::new_v4().to_string(), &token).map(|artifact|
+                artifact.path().to_path_buf()
+            )
+        };
+        let on_event = |event: BatchBookEvent| {
+            window.emit("event-batch-book", event).ok();
+        };
+        let summary = run_batch(&id, &books, request.concurrency(series), &token, convert, on_event);
+        jobs.finish(&id);
+        window.emit("event-batch", summary).ok();
+    });
+    Ok(batch_id)
+}
+
+// the jobs run before, the newest first.
+#[tauri::command]
+fn list_history(filter: HistoryFilter) -> Result<Vec<HistoryEntry>, String> {
+    History::default().list(&filter)
+}
+
+#[tauri::command]
+fn delete_history(ids: Vec<String>) -> Result<usize, String> {
+    History::default().delete(&ids)
+}
+
+// runs a job of the history again with the same settings, returns the new job's id.
+#[tauri::command]
+fn rerun_job<R: Runtime>(
+    window: tauri::Window<R>,
+    jobs: State<'_, Jobs>,
+    id: &str
+) -> Result<String, String> {
+    let entry = History::default()
+        .get(id)?
+        .ok_or(format!("The job {} is not in the history.", id))?;
+    if !Path::new(&entry.book).exists() {
+        return Err(format!("{} is not found.", entry.book));
+    }
+    Ok(spawn_job(window, jobs.inner().clone(), entry.payload))
+}
+
+#[tauri::command]
+fn cancel_job(jobs: State<'_, Jobs>, job_id: &str) -> Result<(), String> {
+    if jobs.cancel(job_id) {
+        Ok(())
+    } else {
+        Err(format!("The job {} is not running.", job_id))
+    }
+}
+
+// run the job and keep it in the history, however it ends.
+fn run_recorded_job<R: Runtime>(
+    window: &tauri::Window<R>,
+    reporter: &ProgressReporter<R>,
+    payload: &Payload,
+    job_id: &str,
+    token: &CancelToken
+) -> Result<Artifact, String> {
+    let started = SystemTime::now();
+    let outcome = run_job(window, reporter, payload);
+    let artifact = outcome.as_ref().ok().map(|artifact| artifact.path());
+    let job = JobResult::new(
+        job_id,
+        outcome.as_ref().map(|artifact| artifact.message()).map_err(|e| e.clone()),
+        token
     );
+    // a history that can't be written doesn't make the job fail, the user is only told
+    if let Err(error) = History::default().add(HistoryEntry::new(payload, &job, artifact, started)) {
+        let text = format!("The job isn't kept in the history: {}", error);
+        window.emit("event-workmesg", WorkMesg::new("text-yellow-800 dark:text-yellow-300", &text)).ok();
+    }
+    outcome
+}
 
-    process(
-        html_file.as_str(),
-        (&payload).language.as_str(),
-        (&payload).show_phoneme,
-        if (&payload).allow_long {
-            2
-        } else {
-            1
-        },
-        (&payload).hint_level,
-        (&payload).wordwise_style,
-        Some(&reporter)
-    )?;
+// annotate the book, returns where it was saved.
+fn run_job<R: Runtime>(
+    window: &tauri::Window<R>,
+    reporter: &ProgressReporter<R>,
+    payload: &Payload
+) -> Result<Artifact, String> {
+    // the workspace is removed while unwinding, the job still has to end for the UI
+    panic::catch_unwind(
+        AssertUnwindSafe(|| {
+            window.emit("event-progress", 0.0).map_err(|e| e.to_string())?;
+            // everything calibre writes on the way goes there, and is gone when the job is over
+            let workspace = Workspace::create(&workspace_root())?;
+            annotate_book(window, reporter, payload, &workspace)
+        })
+    ).unwrap_or_else(|_| Err("The job stopped unexpectedly.".to_string()))
+}
+
+fn annotate_book<R: Runtime>(
+    window: &tauri::Window<R>,
+    reporter: &ProgressReporter<R>,
+    payload: &Payload,
+    workspace: &Workspace
+) -> Result<Artifact, String> {
+    let book = payload.book.as_str();
+    let book_name_without_ext = Path::new(book).file_stem().unwrap().to_str().unwrap();
+    let default_dir = load_settings()
+        .map(|(settings, _)| settings.output_dir)
+        .unwrap_or_default();
+    let folder = output_folder(payload, &default_dir);
+    let artifact_name = output_name(payload)?;
+    // find out before the long conversion whether the book is skipped or can't be written at all
+    let artifact = destination(&folder, &artifact_name, payload.overwrite);
+    if let Artifact::Skipped(_) = artifact {
+        return Ok(artifact);
+    }
+    check_not_source(Path::new(book), artifact.path())?;
+    check_writable(&folder)?;
+    let conversion_args = payload.conversion.args(&payload.format)?;
+    let book_out_dir = convert_to_html(window, reporter, book, workspace)?;
+
+    let html_file = format!("{}/index1.html", book_out_dir);
+    // the book is written in the workspace first, a cancelled calibre leaves nothing next to the user's book.
+    // calibre picks the format from the extension, the output name may not end with it.
+    let artifact_temp = workspace.join(&format!("{}.{}", book_name_without_ext, payload.format));
+    let artifact_temp = artifact_temp.to_str().unwrap();
+
+    let detected = if payload.detect_names {
+        let html = std::fs::read_to_string(&html_file).map_err(|e| e.to_string())?;
+        detect_names(html.as_str())
+    } else {
+        Vec::new()
+    };
+    // the UI shows the detected names so the user can confirm or override them for the next run
+    window.emit("event-names", &detected).map_err(|e| e.to_string())?;
+    let excluded = exclusion_list(&detected, &payload.excluded_names, &payload.included_names);
+
+    // a series goes on from the books annotated before this one
+    let book_file_name = Path::new(book).file_name().unwrap().to_str().unwrap();
+    let series_start = match payload.series() {
+        Some(name) => load_series(name)?.progress_before(book_file_name),
+        None => SeriesProgress::default(),
+    };
+    let mut series_end = series_start.clone();
+    process(html_file.as_str(), payload, &excluded, &mut series_end, Some(&reporter.within(0.2, 0.9)))?;
     window
         .emit(
             "event-workmesg",
@@ -141,17 +397,161 @@ async fn start_job<R: Runtime>(
         )
         .map_err(|e| e.to_string())?;
     let meta_file = format!("{}/content.opf", book_out_dir);
-    run_command(
-        EBOOK_CONVERT,
-        Some(&reporter),
-        &[html_file.as_str(), artifact_file.as_str(), "-m", meta_file.as_str()]
+    let mut args = vec![html_file.as_str(), artifact_temp, "-m", meta_file.as_str()];
+    args.extend(conversion_args.iter().map(|arg| arg.as_str()));
+    run_command(&ebook_convert()?, Some(&reporter.within(0.9, 1.0)), &args)?;
+    reporter.check_cancelled()?;
+    // another book of a batch may have taken the name in the meantime
+    let artifact = destination(&folder, &artifact_name, payload.overwrite);
+    if let Artifact::Saved(artifact_file) = &artifact {
+        check_not_source(Path::new(book), artifact_file)?;
+        move_file(Path::new(artifact_temp), artifact_file)?;
+        // only a saved book counts for the series, a failed or cancelled one is annotated again from the same place
+        if let Some(name) = payload.series() {
+            record_series_book(name, book_file_name, &series_start, &series_end)?;
+        }
+    }
+    reporter.report(1.0);
+    Ok(artifact)
+}
+
+// how hard the book is for a learner, before annotating it. returns the job's id right away, the report comes
+// with "event-book-report" before "event-job" tells how the job ended.
+#[tauri::command]
+fn analyze_book<R: Runtime>(
+    window: tauri::Window<R>,
+    jobs: State<'_, Jobs>,
+    payload: Payload
+) -> Result<String, String> {
+    if payload.book.is_empty() {
+        return Err("Empty book path, please select a book.".to_string());
+    }
+
+    Ok(
+        spawn(window, jobs.inner().clone(), move |window, reporter, _, _| {
+            let report = report_book(window, reporter, &payload)?;
+            window.emit("event-book-report", report).map_err(|e| e.to_string())?;
+            Ok(String::new())
+        })
+    )
+}
+
+fn report_book<R: Runtime>(
+    window: &tauri::Window<R>,
+    reporter: &ProgressReporter<R>,
+    payload: &Payload
+) -> Result<BookReport, String> {
+    window.emit("event-progress", 0.0).map_err(|e| e.to_string())?;
+    let book_language = payload.book_language.as_str();
+    let workspace = Workspace::create(&workspace_root())?;
+    let book_out_dir = convert_to_html(window, reporter, &payload.book, &workspace)?;
+    reporter.check_cancelled()?;
+
+    let html = std::fs::read_to_string(format!("{}/index1.html", book_out_dir)).map_err(|e|
+        e.to_string()
     )?;
-    // remove the temp files and folders
-    std::fs::remove_file(book_dump).map_err(|e| e.to_string())?;
-    std::fs::remove_dir_all(book_out_dir).map_err(|e| e.to_string())?;
+    let (lemma, dict) = load_dictionaries(payload)?;
+    let segmenter = Segmenter::new(book_language, &dict);
+    let report = analyze(html.as_str(), &dict, &lemma, &segmenter, book_language);
     window.emit("event-progress", 100.0).map_err(|e| e.to_string())?;
-    let artifact_file = Path::new(artifact_file.as_str()).file_name().unwrap().to_str().unwrap();
-    Ok(format!("{} save to {}", artifact_file, book_path))
+    Ok(report)
+}
+
+#[tauri::command]
+fn export_book_report(report: BookReport, path: &str) -> Result<(), String> {
+    export_report(&report, path)
+}
+
+// the placement test being taken, from start_placement_test to finish_placement_test.
+struct PlacementState(Mutex<Option<PlacementTest>>);
+
+// the words the reader is asked about, "do you know this word?".
+#[tauri::command]
+fn start_placement_test(
+    state: State<'_, PlacementState>,
+    payload: Payload
+) -> Result<Vec<PlacementQuestion>, String> {
+    let book_language = payload.book_language.as_str();
+    let dict = load_dict(book_language, payload.language.as_str()).map_err(|err|
+        format!("dictionary-{}-{}: {}", book_language, payload.language, err)
+    )?;
+    let test = PlacementTest::new(&dict, Uuid::new_v4().as_u128() as u64);
+    let questions = test.questions.clone();
+    *state.0.lock().map_err(|e| e.to_string())? = Some(test);
+    Ok(questions)
+}
+
+#[tauri::command]
+fn finish_placement_test(
+    state: State<'_, PlacementState>,
+    answers: Vec<PlacementAnswer>
+) -> Result<PlacementResult, String> {
+    let test = state.0
+        .lock()
+        .map_err(|e| e.to_string())?
+        .take()
+        .ok_or("The placement test has not been started.".to_string())?;
+    Ok(test.score(&answers))
+}
+
+#[tauri::command]
+fn list_presets() -> Result<Vec<Preset>, String> {
+    Presets::default().list()
+}
+
+#[tauri::command]
+fn create_preset(preset: Preset) -> Result<Preset, String> {
+    Presets::default().create(preset)
+}
+
+#[tauri::command]
+fn rename_preset(name: &str, new_name: &str) -> Result<(), String> {
+    Presets::default().rename(name, new_name)
+}
+
+#[tauri::command]
+fn delete_preset(name: &str) -> Result<(), String> {
+    Presets::default().delete(name)
+}
+
+#[tauri::command]
+fn import_preset(path: &str) -> Result<Preset, String> {
+    Presets::default().import(Path::new(path))
+}
+
+#[tauri::command]
+fn export_preset(name: &str, path: &str) -> Result<(), String> {
+    Presets::default().export(name, Path::new(path))
+}
+
+// the preset and books given on the command line.
+struct StartupState(StartupOptions);
+
+#[tauri::command]
+fn startup_options(state: State<'_, StartupState>) -> StartupOptions {
+    state.0.clone()
+}
+
+// the frontend gets the settings with "settings_retrived" too.
+#[tauri::command]
+fn read_settings<R: Runtime>(window: tauri::Window<R>) -> Result<AppSetting, String> {
+    let (settings, problems) = load_settings()?;
+    if !problems.is_empty() {
+        let text = format!("Settings: {}, the defaults are used instead.", problems.join(", "));
+        window
+            .emit("event-workmesg", WorkMesg::new("text-red-800 dark:text-red-300", &text))
+            .map_err(|e| e.to_string())?;
+    }
+    window.emit("settings_retrived", &settings).map_err(|e| e.to_string())?;
+    Ok(settings)
+}
+
+#[tauri::command]
+fn save_settings(settings: AppSetting) -> Result<(), String> {
+    store_settings(&settings)?;
+    // the ebook-convert path may have changed
+    forget_ebook_convert();
+    Ok(())
 }
 
 fn setup_data(app: &mut tauri::App) -> Result<(), Box<dyn Error>> {
@@ -161,19 +561,59 @@ fn setup_data(app: &mut tauri::App) -> Result<(), Box<dyn Error>> {
         let app_resource = resource.join(RESORUCE_FOLDER);
         APP_DATA_DIR.set(app_resource.to_string_lossy().into_owned()).ok();
     }
+    if let Some(config) = app_config_dir(&app.config()) {
+        CONFIG_DIR.set(config.to_string_lossy().into_owned()).ok();
+    }
+    if let Some(data) = app_data_dir(&app.config()) {
+        DATA_DIR.set(data.to_string_lossy().into_owned()).ok();
+    }
+    if let Some(cache) = app_cache_dir(&app.config()) {
+        CACHE_DIR.set(cache.to_string_lossy().into_owned()).ok();
+    }
+    // the workspaces of runs which crashed
+    sweep(&workspace_root(), ORPHAN_AGE);
     Ok(())
 }
 fn main() {
+    let startup = match parse_args(std::env::args().skip(1)) {
+        Ok(startup) => startup,
+        Err(error) => {
+            eprintln!("{}\nusage: ebook-wordwise [--preset <name>] [book or folder...]", error);
+            std::process::exit(2);
+        }
+    };
     Builder::default()
         .invoke_handler(
-            tauri::generate_handler![start_job, check_ebook_convert, preview, open_directory]
+            tauri::generate_handler![
+                start_job,
+                start_batch,
+                cancel_job,
+                list_history,
+                delete_history,
+                rerun_job,
+                read_settings,
+                save_settings,
+                list_presets,
+                create_preset,
+                rename_preset,
+                delete_preset,
+                import_preset,
+                export_preset,
+                startup_options,
+                check_ebook_convert,
+                preview,
+                open_directory,
+                analyze_book,
+                export_book_report,
+                start_placement_test,
+                finish_placement_test
+            ]
         )
+        .manage(PlacementState(Mutex::new(None)))
+        .manage(Jobs::default())
+        .manage(StartupState(startup))
         .setup(setup_data)
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
 
-#[derive(serde::Serialize, Deserialize, Clone, Debug)]
-struct AppSetting {
-    theme: String,
-}
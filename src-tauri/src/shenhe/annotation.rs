@@ -1,18 +1,62 @@
-use super::types::{ annotate_text, Annotator, Clean, Cleaner, DictRecord, APP_DATA_DIR };
+use super::morphology::candidate_lemmas;
+use super::names::is_excluded_name;
+use super::occurrence::push_occurrence;
+use super::phrase::Tokens;
+use super::pos::select_sense;
+use super::tokenize::{ is_dash, split_clitic, split_dashes, split_hyphens };
+use super::types::{
+    annotate_text,
+    is_punctuation,
+    CefrLevel,
+    ChunkParameter,
+    DictRecord,
+    PartOfSpeech,
+    Sense,
+    APP_DATA_DIR,
+};
 use csv::{ Reader, ReaderBuilder };
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::{ Error, ErrorKind };
+use std::io::{ Error, ErrorKind, Read };
 use std::path::PathBuf;
 use std::result::Result;
 
 const WORDWISE_DICTIONARY_PATH: &str = "wordwise-dict.";
-const LEMMA_DICTIONARY_PATH: &str = "lemmatization-en.csv";
+const LEMMA_DICTIONARY_PATH: &str = "lemmatization-";
+const FREQUENCY_LIST_PATH: &str = "frequency-";
+pub const ENGLISH: &str = "en";
+
+// the dictionary for a book in `source` language explained in `target` language, "wordwise-dict.de-en.csv"
+// for german books with english definitions. english books keep the original "wordwise-dict.<target>.csv" name.
+fn dict_file_names(source: &str, target: &str) -> Vec<String> {
+    let mut names = vec![format!("{}{}-{}.csv", WORDWISE_DICTIONARY_PATH, source, target)];
+    if source == ENGLISH {
+        names.push(format!("{}{}.csv", WORDWISE_DICTIONARY_PATH, target));
+    }
+    names
+}
 
-pub fn load_dict(lang: &str) -> Result<HashMap<String, DictRecord>, Error> {
-    let wordwise_dict_path = get_resource_path(
-        format!("{}{}.csv", WORDWISE_DICTIONARY_PATH, lang).as_str()
-    );
+// the resource files a book in `source` language explained in `target` language is annotated with, the ones which
+// exist.
+pub fn resource_files(source: &str, target: &str) -> Vec<PathBuf> {
+    let mut names = dict_file_names(source, target);
+    names.push(format!("{}{}.csv", LEMMA_DICTIONARY_PATH, source));
+    names.push(format!("{}{}.csv", FREQUENCY_LIST_PATH, source));
+    names
+        .iter()
+        .map(|name| get_resource_path(name))
+        .filter(|path| path.exists())
+        .collect()
+}
+
+pub fn load_dict(source: &str, target: &str) -> Result<HashMap<String, DictRecord>, Error> {
+    let names = dict_file_names(source, target);
+    let wordwise_dict_path = names
+        .iter()
+        .map(|name| get_resource_path(name))
+        .find(|path| path.exists())
+        .unwrap_or_else(|| get_resource_path(names.last().unwrap()));
 
     let file = match File::open(&wordwise_dict_path) {
         Ok(file) => file,
@@ -25,7 +69,14 @@ pub fn load_dict(lang: &str) -> Result<HashMap<String, DictRecord>, Error> {
         }
     };
 
-    let mut reader = Reader::from_reader(file);
+    read_dict(file)
+}
+
+// the columns are: id, word, phoneme, full definition, short definition, example sentences, hint level, and the
+// optional part of speech and cefr level. a headword can have one row per part of speech, the first row is its
+// main sense.
+fn read_dict<R: Read>(input: R) -> Result<HashMap<String, DictRecord>, Error> {
+    let mut reader = Reader::from_reader(input);
 
     let mut wordwise_dict: HashMap<String, DictRecord> = HashMap::new();
 
@@ -39,6 +90,16 @@ pub fn load_dict(lang: &str) -> Result<HashMap<String, DictRecord>, Error> {
             record.get(5).unwrap().to_string(),
             record.get(6).unwrap().parse::<i32>().unwrap(),
         );
+        let pos = PartOfSpeech::parse(record.get(7).unwrap_or(""));
+        let cefr = CefrLevel::parse(record.get(8).unwrap_or(""));
+
+        if let Some(existing) = wordwise_dict.get_mut(&word) {
+            if pos != PartOfSpeech::Unknown && existing.pos != pos {
+                existing.senses.push(Sense { pos, full_def, short_def });
+                continue;
+            }
+        }
+
         wordwise_dict.insert(word.clone(), DictRecord {
             word: word.clone(),
             phoneme,
@@ -46,6 +107,10 @@ pub fn load_dict(lang: &str) -> Result<HashMap<String, DictRecord>, Error> {
             short_def,
             example_sentences,
             hint_lvl,
+            pos,
+            senses: Vec::new(),
+            frequency_rank: None,
+            cefr,
         });
     }
     //println!("{:?}", wordwise_dict.get("amperage"));
@@ -53,10 +118,22 @@ pub fn load_dict(lang: &str) -> Result<HashMap<String, DictRecord>, Error> {
     Ok(wordwise_dict)
 }
 
-pub fn load_lemma() -> Result<HashMap<String, String>, Error> {
-    let lemma_dict_path = get_resource_path(LEMMA_DICTIONARY_PATH);
+pub fn load_lemma(lang: &str) -> Result<HashMap<String, String>, Error> {
+    let lemma_dict_path = get_resource_path(
+        format!("{}{}.csv", LEMMA_DICTIONARY_PATH, lang).as_str()
+    );
 
-    let file = File::open(lemma_dict_path)?;
+    let file = match File::open(&lemma_dict_path) {
+        Ok(file) => file,
+        // not every language inflects its words, no table means no lemmatization
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            println!("{} not found", lemma_dict_path.to_str().unwrap());
+            return Ok(HashMap::new());
+        }
+        Err(e) => {
+            return Err(e);
+        }
+    };
     let mut reader = ReaderBuilder::new().has_headers(true).from_reader(file);
     let lemma_dict: HashMap<_, _> = reader
         .records()
@@ -72,6 +149,49 @@ pub fn load_lemma() -> Result<HashMap<String, String>, Error> {
     Ok(lemma_dict)
 }
 
+// the word frequency list of a language, "frequency-en.csv", its words ranked from 1 in the order they're listed.
+pub fn load_frequency(lang: &str) -> Result<HashMap<String, usize>, Error> {
+    let frequency_list_path = get_resource_path(
+        format!("{}{}.csv", FREQUENCY_LIST_PATH, lang).as_str()
+    );
+
+    let file = match File::open(&frequency_list_path) {
+        Ok(file) => file,
+        Err(e) => {
+            if e.kind() == ErrorKind::NotFound {
+                println!("{} not found", frequency_list_path.to_str().unwrap());
+            }
+            return Err(e);
+        }
+    };
+
+    read_frequency(file)
+}
+
+// one word per line, the most frequent first. anything after the word, a count for example, is ignored.
+fn read_frequency<R: Read>(input: R) -> Result<HashMap<String, usize>, Error> {
+    let mut reader = ReaderBuilder::new().has_headers(false).flexible(true).from_reader(input);
+    let mut ranks = HashMap::new();
+
+    for result in reader.records() {
+        let record = result?;
+        let word = record.get(0).unwrap_or("").trim().to_lowercase();
+        if !word.is_empty() {
+            let rank = ranks.len() + 1;
+            ranks.entry(word).or_insert(rank);
+        }
+    }
+
+    Ok(ranks)
+}
+
+// give every headword its rank in the frequency list, the headwords missing from it stay without a rank.
+pub fn rank_by_frequency(dict: &mut HashMap<String, DictRecord>, ranks: &HashMap<String, usize>) {
+    for dr in dict.values_mut() {
+        dr.frequency_rank = ranks.get(&dr.word.to_lowercase()).copied();
+    }
+}
+
 fn get_resource_path(resource_name: &str) -> PathBuf {
     if let Some(path) = APP_DATA_DIR.get() {
         let path = path.clone();
@@ -81,67 +201,48 @@ fn get_resource_path(resource_name: &str) -> PathBuf {
     std::env::current_dir().unwrap().join("resources").join(resource_name)
 }
 
-pub fn annotate_phrase(
-    annotator: &Annotator,
-    sentence: &str,
-    dict: &HashMap<String, DictRecord>,
-    lemma_dict: &HashMap<String, String>,
-    def_length: i32
-) -> String {
-    let words: Vec<&str> = sentence.split_whitespace().collect();
-    let mut result = String::new();
+pub fn annotate_phrase(sentence: &str, param: &ChunkParameter) -> String {
+    let dict = param.dict;
+    let words: Vec<&str> = param.segmenter.segment(sentence, dict);
+    let separator = param.segmenter.separator();
+    let mut result = String::with_capacity(sentence.len());
+    // the words of a matched phrase are put together here, one buffer for the whole sentence
+    let mut phrase = String::new();
+    let tokens = Tokens::new(&words);
     let mut i = 0;
-    let max_phrase_length = 5;
 
     while i < words.len() {
-        let mut longest_match = None;
-        let mut longest_length = 0;
-
-        // Try to find the longest phrase in the dictionary, up to max_phrase_length words
-        for j in i + 1..=words.len() {
-            if j - i > max_phrase_length {
-                break;
-            }
-            let phrase: String = words[i..j].join(" ");
-            let (cleaned_phrase, _, _) = Cleaner::clean_word(&phrase, false);
-
-            if dict.contains_key(&cleaned_phrase) {
-                let length = j - i;
-                if length > longest_length {
-                    longest_length = length;
-                    longest_match = Some(phrase);
+        // Try to find the longest phrase in the dictionary, up to the matcher's max phrase length
+        let longest_match = param.matcher
+            .longest_match(&tokens, i, param.lemma)
+            .filter(|(_, length)| *length > 1 || !is_excluded_name(words[i], param.excluded));
+        if let Some((headword, length)) = longest_match {
+            phrase.clear();
+            for (k, word) in words[i..i + length].iter().enumerate() {
+                if k > 0 {
+                    phrase.push_str(separator);
                 }
+                phrase.push_str(word);
             }
-        }
-
-        if let Some(phrase) = longest_match {
-            //println!("{} -> {}", phrase, longest_length);
-            let dict_record = get_dict_record(phrase.as_str(), dict, lemma_dict);
-            match dict_record {
+            match dict.get(headword) {
+                // a single word is matched through its lemma too, "uttering" needs its verb sense
+                Some(dr) if length == 1 => {
+                    let dr = select_sense(dr, &words, i);
+                    push_annotation(&dr, phrase.as_str(), param, &mut result);
+                }
                 Some(dr) => {
-                    result.push_str(
-                        &format!("{} ", annotate_text(annotator, dr, phrase.as_str(), def_length))
-                    );
+                    push_annotation(dr, phrase.as_str(), param, &mut result);
                 }
                 None => {
-                    result.push_str(&format!("{} ", phrase.as_str()));
+                    result.push_str(phrase.as_str());
                 }
             }
-            i += longest_length;
+            result.push_str(separator);
+            i += length;
         } else {
             // If no phrase matches, check for individual word match
-            let dict_record = get_dict_record(words[i], dict, lemma_dict);
-
-            match dict_record {
-                Some(dr) => {
-                    result.push_str(
-                        &format!("{} ", annotate_text(annotator, dr, words[i], def_length))
-                    );
-                }
-                None => {
-                    result.push_str(&format!("{} ", words[i]));
-                }
-            }
+            annotate_word(words[i], &words, i, param, &mut result);
+            result.push_str(separator);
             i += 1;
         }
     }
@@ -150,6 +251,75 @@ pub fn annotate_phrase(
     restore_whitespace(sentence, result.trim_end())
 }
 
+// annotate a word which doesn't start a phrase. when it isn't in the dictionary as written it's cut at its
+// dashes, contraction or hyphens and the parts are tried instead, the punctuation stays where it was.
+fn annotate_word(word: &str, words: &[&str], i: usize, param: &ChunkParameter, result: &mut String) {
+    if let Some(dr) = lookup_word(word, param) {
+        let dr = select_sense(dr, words, i);
+        push_annotation(&dr, word, param, result);
+        return;
+    }
+
+    let pieces = split_dashes(word);
+    if !pieces.is_empty() {
+        for piece in pieces {
+            if is_dash(piece) {
+                result.push_str(piece);
+            } else {
+                annotate_word(piece, words, i, param, result);
+            }
+        }
+        return;
+    }
+
+    if let Some((core_end, lookup)) = split_clitic(word) {
+        let dict_record = match lookup {
+            Some(lookup) => param.dict.get(lookup),
+            None => lookup_word(&word[..core_end], param),
+        };
+        if let Some(dr) = dict_record {
+            let dr = select_sense(dr, words, i);
+            push_annotation(&dr, &word[..core_end], param, result);
+            result.push_str(&word[core_end..]);
+            return;
+        }
+    }
+
+    let pieces = split_hyphens(word);
+    if !pieces.is_empty() {
+        for piece in pieces {
+            if piece == "-" {
+                result.push_str(piece);
+            } else {
+                annotate_word(piece, words, i, param, result);
+            }
+        }
+        return;
+    }
+
+    result.push_str(word);
+}
+
+// with an occurrence or density limit, or a fade, the annotation is only kept if it turns out to be among the
+// first ones, the rarest words around it, or still wanted where it is in the book.
+fn push_annotation(dr: &DictRecord, target: &str, param: &ChunkParameter, result: &mut String) {
+    let annotated = annotate_text(param.annotator, dr, target, param.def_length);
+    if param.limits_occurrences() && annotated != target {
+        let rank = param.annotator.difficulty().rarity_rank(dr);
+        push_occurrence(result, dr, rank, &annotated, target);
+    } else {
+        result.push_str(&annotated);
+    }
+}
+
+// names are left alone
+fn lookup_word<'a>(word: &str, param: &ChunkParameter<'a>) -> Option<&'a DictRecord> {
+    if is_excluded_name(word, param.excluded) {
+        return None;
+    }
+    get_dict_record(word, param.dict, param.lemma, param.book_language)
+}
+
 fn restore_whitespace(sentence: &str, replaced_sentence: &str) -> String {
     // Capture the leading whitespace
     let leading_whitespace = sentence
@@ -180,25 +350,42 @@ fn restore_whitespace(sentence: &str, replaced_sentence: &str) -> String {
     result
 }
 
-fn get_dict_record<'a>(
+pub fn get_dict_record<'a>(
     word: &str,
     wordwise_dict: &'a HashMap<String, DictRecord>,
-    lemma_dict: &HashMap<String, String>
+    lemma_dict: &HashMap<String, String>,
+    book_language: &str
 ) -> Option<&'a DictRecord> {
-    let (clean_word, _, _) = Cleaner::clean_word(word, true);
-    //println!("{} -> {}, {:?}", word, clean_word, wordwise_dict.get(clean_word.as_str()));
-    if let Some(dict_record) = wordwise_dict.get(clean_word.as_str()) {
+    let trimmed = word.trim_matches(is_punctuation);
+    // most words in a book are lowercase already, only allocate when we have to
+    let clean_word = if trimmed.chars().any(|c| c.is_uppercase() || c == '’') {
+        Cow::Owned(trimmed.to_lowercase().replace('’', "'"))
+    } else {
+        Cow::Borrowed(trimmed)
+    };
+    //println!("{} -> {}, {:?}", word, clean_word, wordwise_dict.get(clean_word.as_ref()));
+    if let Some(dict_record) = wordwise_dict.get(clean_word.as_ref()) {
         return Some(dict_record);
     }
 
     // Not found, and it's not a phrase, find its normal form
     if !word.contains(' ') {
-        if let Some(normal_form) = lemma_dict.get(clean_word.as_str()) {
+        if let Some(normal_form) = lemma_dict.get(clean_word.as_ref()) {
             // Then, find the normal form word in the wordwise dictionary
             if let Some(dict_record) = wordwise_dict.get(normal_form) {
                 return Some(dict_record);
             }
         }
+
+        // The lemmatization table doesn't know it, guess the normal form by the inflection rules
+        if book_language != ENGLISH {
+            return None;
+        }
+        for candidate in candidate_lemmas(clean_word.as_ref()) {
+            if let Some(dict_record) = wordwise_dict.get(candidate.as_str()) {
+                return Some(dict_record);
+            }
+        }
     }
 
     None
@@ -206,13 +393,17 @@ fn get_dict_record<'a>(
 
 #[cfg(test)]
 mod tests {
+    use super::super::occurrence::{ resolve_occurrences, OccurrenceLimit };
+    use super::super::phrase::{ PhraseMatcher, DEFAULT_MAX_PHRASE_LENGTH };
+    use super::super::segment::Segmenter;
     use super::super::types::*;
     use super::*;
+    use std::collections::HashSet;
 
     #[test]
     fn test_load_dict() {
         let lang = "en";
-        let result = load_dict(lang);
+        let result = load_dict(lang, lang);
 
         match result {
             Ok(dict) => {
@@ -228,7 +419,7 @@ mod tests {
 
     #[test]
     fn test_load_lemma() {
-        let result = load_lemma();
+        let result = load_lemma("en");
         match result {
             Ok(dict) => {
                 assert!(!dict.is_empty());
@@ -241,6 +432,76 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_read_dict_senses() {
+        let csv = "id,word,phoneme,full_def,short_def,example_sentences,hint_lvl,pos
+1,utter,,complete and total,complete and total,,4,adj
+2,utter,,to say something out loud,to say,,4,verb
+3,ribose,,a sugar found in RNA,a kind of sugar,,5,
+";
+        let dict = read_dict(csv.as_bytes()).unwrap();
+        let utter = dict.get("utter").unwrap();
+        assert_eq!(utter.pos, PartOfSpeech::Adjective);
+        assert_eq!(utter.short_def, "complete and total");
+        assert_eq!(utter.senses.len(), 1);
+        assert_eq!(utter.for_pos(PartOfSpeech::Verb).short_def, "to say");
+        assert_eq!(utter.for_pos(PartOfSpeech::Noun).short_def, "complete and total");
+        assert_eq!(dict.get("ribose").unwrap().pos, PartOfSpeech::Unknown);
+    }
+
+    #[test]
+    fn test_frequency_difficulty() {
+        let list = "the,5000\nsay,800\nSay\nutter,12\n\ncomplete\n";
+        let ranks = read_frequency(list.as_bytes()).unwrap();
+        assert_eq!(ranks.get("say"), Some(&2));
+        // "Say" is the same word again and doesn't take a rank
+        assert_eq!(ranks.get("utter"), Some(&3));
+
+        let csv = "id,word,phoneme,full_def,short_def,example_sentences,hint_lvl
+1,say,,to speak words,to speak,,5
+2,utter,,to say something out loud,to say,,4
+3,ribose,,a sugar found in RNA,a kind of sugar,,5
+";
+        let mut dict = read_dict(csv.as_bytes()).unwrap();
+        rank_by_frequency(&mut dict, &ranks);
+        assert_eq!(dict.get("ribose").unwrap().frequency_rank, None);
+
+        let difficulty = Difficulty::OutsideTop(2);
+        assert_eq!(dict.get("say").unwrap().get_meaning(1, difficulty, false), "");
+        assert_eq!(dict.get("utter").unwrap().get_meaning(1, difficulty, false), "to say");
+        assert_eq!(dict.get("ribose").unwrap().get_meaning(1, difficulty, false), "a kind of sugar");
+        assert!(
+            difficulty.rarity_rank(dict.get("ribose").unwrap()) <
+                difficulty.rarity_rank(dict.get("utter").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_cefr_difficulty() {
+        let csv = "id,word,phoneme,full_def,short_def,example_sentences,hint_lvl,pos,cefr
+1,sociable,,liking to meet people,friendly,,3,adj,b1
+2,versatile,,able to do many things,able to do different things,,2,,
+3,happy,,feeling pleasure,glad,,5,,A1
+";
+        let dict = read_dict(csv.as_bytes()).unwrap();
+        assert_eq!(dict.get("sociable").unwrap().cefr, Some(CefrLevel::B1));
+        // no level in the row, it comes from hint level 2
+        assert_eq!(dict.get("versatile").unwrap().cefr_level(), CefrLevel::C1);
+
+        let difficulty = Difficulty::AboveCefr(CefrLevel::B1);
+        assert_eq!(dict.get("sociable").unwrap().get_meaning(1, difficulty, false), "");
+        assert_eq!(dict.get("versatile").unwrap().get_meaning(1, difficulty, false), "able to do different things");
+        assert_eq!(dict.get("happy").unwrap().get_meaning(1, Difficulty::AboveCefr(CefrLevel::A1), false), "");
+        assert_eq!(CefrLevel::parse(" c2"), Some(CefrLevel::C2));
+        assert_eq!(CefrLevel::parse("D1"), None);
+    }
+
+    #[test]
+    fn test_dict_file_names() {
+        assert_eq!(dict_file_names("en", "cn"), vec!["wordwise-dict.en-cn.csv", "wordwise-dict.cn.csv"]);
+        assert_eq!(dict_file_names("de", "en"), vec!["wordwise-dict.de-en.csv"]);
+    }
+
     #[test]
     fn test_clean_word() {
         let test_cases = vec![
@@ -264,9 +525,9 @@ mod tests {
     #[test]
     fn test_get_dict_record() {
         let word = "riboses";
-        let wordwise_dict = load_dict("en").unwrap();
-        let lemma_dict = load_lemma().unwrap();
-        let result = get_dict_record(word, &wordwise_dict, &lemma_dict);
+        let wordwise_dict = load_dict("en", "en").unwrap();
+        let lemma_dict = load_lemma("en").unwrap();
+        let result = get_dict_record(word, &wordwise_dict, &lemma_dict, "en");
         assert!(result.is_some());
         match result {
             Some(dict_record) => {
@@ -282,26 +543,26 @@ mod tests {
     #[test]
     fn test_get_meaning() {
         let word = "pictorial";
-        let dict = load_dict("en").unwrap();
+        let dict = load_dict("en", "en").unwrap();
         let dict_record = dict.get(word).unwrap();
         assert_eq!(dict_record.phoneme, "/pɪkˈtɔriəl/");
         assert_eq!(dict_record.full_def, "of or relating to painting or drawing");
-        let res = dict_record.get_meaning(2, 3, true);
+        let res = dict_record.get_meaning(2, Difficulty::HintLevel(3), true);
         assert_eq!(res, "/pɪkˈtɔriəl/ of or relating to painting or drawing");
     }
 
     #[test]
     fn test_wrap_with_ruby_tag() {
         let word = "pictorials.";
-        let dict = load_dict("en").unwrap();
+        let dict = load_dict("en", "en").unwrap();
         let dict_record = dict.get("pictorial").unwrap();
-        let anotator = Annotator::RubyAnnotator(4, true);
+        let anotator = Annotator::RubyAnnotator(Difficulty::HintLevel(4), true);
         let res = annotate_text(&anotator, &dict_record, word, 2);
         assert_eq!(
             res,
             "<ruby>pictorials<rt>/pɪkˈtɔriəl/ of or relating to painting or drawing</rt></ruby>."
         );
-        let anotator = Annotator::RubyAnnotator(4, false);
+        let anotator = Annotator::RubyAnnotator(Difficulty::HintLevel(4), false);
         let res = annotate_text(&anotator, &dict_record, word, 1);
         assert_eq!(res, "<ruby>pictorials<rt>relating to a drawing</rt></ruby>.");
     }
@@ -347,35 +608,179 @@ mod tests {
             ("<b>This is the time</b> we need.", "<b>This is the time</b> we need.", 1)
         ];
 
-        let hashes = load_dict("en").unwrap();
-        let lemma = load_lemma().unwrap();
+        let hashes = load_dict("en", "en").unwrap();
+        let lemma = load_lemma("en").unwrap();
+        let matcher = PhraseMatcher::new(&hashes, DEFAULT_MAX_PHRASE_LENGTH);
         for (input, output, lvl) in data {
-            let anotator = Annotator::RubyAnnotator(lvl, false);
-            let result = annotate_phrase(&anotator, input, &hashes, &lemma, 1);
+            let anotator = Annotator::RubyAnnotator(Difficulty::HintLevel(lvl), false);
+            let param = test_param(&hashes, &lemma, &matcher, &anotator);
+            let result = annotate_phrase(input, &param);
             assert_eq!(result, output);
         }
     }
 
+    #[test]
+    fn test_annotate_phrase_senses() {
+        let csv = "id,word,phoneme,full_def,short_def,example_sentences,hint_lvl,pos
+1,utter,,complete and total,complete and total,,1,adj
+2,utter,,to say something out loud,to say,,1,verb
+3,a word of,,a little,a little,,1,
+";
+        let dict = read_dict(csv.as_bytes()).unwrap();
+        let lemma = HashMap::from([("uttering".to_string(), "utter".to_string())]);
+        let matcher = PhraseMatcher::new(&dict, DEFAULT_MAX_PHRASE_LENGTH);
+        let anotator = Annotator::RubyAnnotator(Difficulty::HintLevel(5), false);
+        let param = test_param(&dict, &lemma, &matcher, &anotator);
+        assert_eq!(
+            annotate_phrase("no one uttering a word of it, utter nonsense.", &param),
+            "no one <ruby>uttering<rt>to say</rt></ruby> <ruby>a word of<rt>a little</rt></ruby> it, <ruby>utter<rt>complete and total</rt></ruby> nonsense."
+        );
+    }
+
     #[test]
     fn test_color_annotate() {
         let data = vec![
             (
                 "two <span>unreasonable</span> versatile one.",
                 "two <span>unreasonable</span> <span style='color:red'>versatile</span> one.",
-                Annotator::ColorAnnotator("red", 4, false),
+                Annotator::ColorAnnotator("red", Difficulty::HintLevel(4), false),
             ),
             (
                 "two <span>unreasonable</span> versatile one.",
                 "two <span>unreasonable</span> <span style='color:blue'>versatile</span> one.",
-                Annotator::ColorAnnotator("blue", 4, false),
+                Annotator::ColorAnnotator("blue", Difficulty::HintLevel(4), false),
             )
         ];
 
-        let hashes = load_dict("en").unwrap();
-        let lemma = load_lemma().unwrap();
+        let hashes = load_dict("en", "en").unwrap();
+        let lemma = load_lemma("en").unwrap();
+        let matcher = PhraseMatcher::new(&hashes, DEFAULT_MAX_PHRASE_LENGTH);
         for (input, output, annotator) in data {
-            let result = annotate_phrase(&annotator, input, &hashes, &lemma, 1);
+            let param = test_param(&hashes, &lemma, &matcher, &annotator);
+            let result = annotate_phrase(input, &param);
             assert_eq!(result, output);
         }
     }
+
+    #[test]
+    fn test_annotate_skips_names() {
+        let hashes = load_dict("en", "en").unwrap();
+        let lemma = load_lemma("en").unwrap();
+        let matcher = PhraseMatcher::new(&hashes, DEFAULT_MAX_PHRASE_LENGTH);
+        let annotator = Annotator::RubyAnnotator(Difficulty::HintLevel(4), false);
+        let excluded: HashSet<String> = ["versatile".to_string()].into_iter().collect();
+        let param = ChunkParameter {
+            excluded: &excluded,
+            ..test_param(&hashes, &lemma, &matcher, &annotator)
+        };
+
+        let result = annotate_phrase("Versatile's versatile friend.", &param);
+        assert_eq!(
+            result,
+            "Versatile's <ruby>versatile<rt>able to do different things</rt></ruby> friend."
+        );
+    }
+
+    #[test]
+    fn test_annotate_compounds() {
+        let mut hashes = HashMap::new();
+        for word in ["well-being", "sociable", "versatile", "teacher", "o'clock", "will"] {
+            hashes.insert(word.to_string(), DictRecord {
+                word: word.to_string(),
+                short_def: format!("def of {}", word),
+                hint_lvl: 1,
+                ..Default::default()
+            });
+        }
+        let lemma = HashMap::new();
+        let matcher = PhraseMatcher::new(&hashes, DEFAULT_MAX_PHRASE_LENGTH);
+        let annotator = Annotator::RubyAnnotator(Difficulty::HintLevel(1), false);
+        let param = test_param(&hashes, &lemma, &matcher, &annotator);
+
+        let data = [
+            ("their well-being.", "their <ruby>well-being<rt>def of well-being</rt></ruby>."),
+            (
+                "sociable—versatile,",
+                "<ruby>sociable<rt>def of sociable</rt></ruby>—<ruby>versatile<rt>def of versatile</rt></ruby>,",
+            ),
+            ("the teacher's,", "the <ruby>teacher<rt>def of teacher</rt></ruby>'s,"),
+            ("at six o’clock", "at six <ruby>o’clock<rt>def of o'clock</rt></ruby>"),
+            ("I won't", "I <ruby>won't<rt>def of will</rt></ruby>"),
+            (
+                "(sociable-versatile)",
+                "(<ruby>sociable<rt>def of sociable</rt></ruby>-<ruby>versatile<rt>def of versatile</rt></ruby>)",
+            ),
+            ("isn't", "isn't"),
+        ];
+        for (input, output) in data {
+            assert_eq!(annotate_phrase(input, &param), output);
+        }
+    }
+
+    #[test]
+    fn test_annotate_first_occurrences() {
+        let mut hashes = HashMap::new();
+        for word in ["sociable", "teacher"] {
+            hashes.insert(word.to_string(), DictRecord {
+                word: word.to_string(),
+                short_def: format!("def of {}", word),
+                hint_lvl: 1,
+                ..Default::default()
+            });
+        }
+        let lemma = HashMap::new();
+        let matcher = PhraseMatcher::new(&hashes, DEFAULT_MAX_PHRASE_LENGTH);
+        let annotator = Annotator::RubyAnnotator(Difficulty::HintLevel(1), false);
+        let param = ChunkParameter {
+            occurrence_limit: OccurrenceLimit { max: 1, per_chapter: false },
+            ..test_param(&hashes, &lemma, &matcher, &annotator)
+        };
+
+        // the later chunk is annotated first, the way rayon may well do it
+        let second = annotate_phrase("a sociable teacher's friend", &param);
+        let first = annotate_phrase("the teacher was sociable", &param);
+        let mut chunks = vec![first, second];
+        resolve_occurrences(
+            &mut chunks,
+            param.occurrence_limit,
+            param.density_limit,
+            param.fade,
+            &mut HashMap::new()
+        );
+        assert_eq!(chunks, vec![
+            "the <ruby>teacher<rt>def of teacher</rt></ruby> was <ruby>sociable<rt>def of sociable</rt></ruby>",
+            "a sociable teacher's friend",
+        ]);
+    }
+
+    #[test]
+    fn test_annotate_unspaced_text() {
+        let mut hashes = HashMap::new();
+        for (word, short_def) in [("一石二鸟", "kill two birds with one stone"), ("办法", "method")] {
+            hashes.insert(word.to_string(), DictRecord {
+                word: word.to_string(),
+                phoneme: "".to_string(),
+                full_def: short_def.to_string(),
+                short_def: short_def.to_string(),
+                example_sentences: "".to_string(),
+                hint_lvl: 1,
+                ..Default::default()
+            });
+        }
+        let lemma = HashMap::new();
+        let matcher = PhraseMatcher::new(&hashes, DEFAULT_MAX_PHRASE_LENGTH);
+        let segmenter = Segmenter::new("cn", &hashes);
+        let annotator = Annotator::RubyAnnotator(Difficulty::HintLevel(1), false);
+        let param = ChunkParameter {
+            segmenter: &segmenter,
+            book_language: "cn",
+            ..test_param(&hashes, &lemma, &matcher, &annotator)
+        };
+
+        let result = annotate_phrase(" 这是一石二鸟的办法。 ", &param);
+        assert_eq!(
+            result,
+            " 这是<ruby>一石二鸟<rt>kill two birds with one stone</rt></ruby>的<ruby>办法<rt>method</rt></ruby>。 "
+        );
+    }
 }
@@ -0,0 +1,384 @@
+use super::job::CancelToken;
+use serde::{ Deserialize, Serialize };
+use std::collections::{ BTreeSet, HashSet };
+use std::path::{ Path, PathBuf };
+use std::sync::{ atomic::{ AtomicUsize, Ordering }, Mutex };
+use std::thread;
+
+// the books calibre reads which are worth annotating.
+const DEFAULT_EXTENSIONS: [&str; 8] = ["epub", "mobi", "azw3", "azw", "fb2", "docx", "rtf", "pdf"];
+// calibre is heavy and every book's html is already annotated on all the cores.
+const DEFAULT_CONCURRENCY: usize = 2;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct BatchRequest {
+    // books and folders of books
+    pub paths: Vec<String>,
+    // the extensions looked for in the folders, without the dot. empty takes the usual ebook formats.
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    #[serde(default)]
+    pub recursive: bool,
+    // how many books are converted at the same time, 0 is the default
+    #[serde(default)]
+    pub concurrency: usize,
+}
+
+impl BatchRequest {
+    // the books of a series continue from each other's state, they're annotated one at a time
+    pub fn concurrency(&self, series: bool) -> usize {
+        if series {
+            1
+        } else if self.concurrency == 0 {
+            DEFAULT_CONCURRENCY
+        } else {
+            self.concurrency
+        }
+    }
+
+    // the books to annotate, the folders' ones sorted by path. books given by name are taken whatever their
+    // extension.
+    pub fn collect_books(&self) -> Result<Vec<PathBuf>, String> {
+        let extensions: Vec<String> = if self.extensions.is_empty() {
+            DEFAULT_EXTENSIONS.iter().map(|ext| ext.to_string()).collect()
+        } else {
+            self.extensions
+                .iter()
+                .map(|ext| ext.trim_start_matches('.').to_lowercase())
+                .collect()
+        };
+
+        let mut books = Vec::new();
+        let mut found = BTreeSet::new();
+        for path in &self.paths {
+            let path = Path::new(path);
+            if path.is_dir() {
+                walk_folder(path, self.recursive, &extensions, &mut found)?;
+            } else if path.is_file() {
+                books.push(path.to_path_buf());
+            } else {
+                return Err(format!("{}: no such book or folder", path.display()));
+            }
+        }
+        for book in found {
+            if !books.contains(&book) {
+                books.push(book);
+            }
+        }
+        Ok(books)
+    }
+}
+
+fn walk_folder(
+    folder: &Path,
+    recursive: bool,
+    extensions: &[String],
+    found: &mut BTreeSet<PathBuf>
+) -> Result<(), String> {
+    let entries = std::fs::read_dir(folder).map_err(|e| format!("{}: {}", folder.display(), e))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if recursive {
+                walk_folder(&path, recursive, extensions, found)?;
+            }
+            continue;
+        }
+        let wanted = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| extensions.contains(&ext.to_lowercase()));
+        if wanted {
+            found.insert(path);
+        }
+    }
+    Ok(())
+}
+
+// the books annotated by an earlier batch are in the folders too, a book which is where another one of the list
+// is saved isn't annotated again. `output_of` is where a book is saved.
+pub fn skip_outputs<F>(books: Vec<PathBuf>, output_of: F) -> Vec<PathBuf> where F: Fn(&Path) -> Option<PathBuf> {
+    let outputs: HashSet<PathBuf> = books
+        .iter()
+        .filter_map(|book| output_of(book).filter(|output| output != book))
+        .collect();
+    books
+        .into_iter()
+        .filter(|book| !outputs.contains(book))
+        .collect()
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BookStatus {
+    Started,
+    Done,
+    Failed,
+    // stopped half way by cancel_job, it isn't among the finished books
+    Cancelled,
+}
+
+// sent with "event-batch-book" when a book starts and when it ends.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct BatchBookEvent {
+    pub batch_id: String,
+    pub book: String,
+    pub status: BookStatus,
+    // the books finished so far, done or failed, and how many there are
+    pub finished: usize,
+    pub total: usize,
+    pub output: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct BookOutcome {
+    pub book: String,
+    pub output: Option<String>,
+    pub error: Option<String>,
+}
+
+// sent with "event-batch" when the batch is over.
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct BatchSummary {
+    pub batch_id: String,
+    pub succeeded: Vec<BookOutcome>,
+    pub failed: Vec<BookOutcome>,
+    // the books left when the batch was cancelled
+    pub cancelled: Vec<String>,
+}
+
+// annotate `books` with `concurrency` of them at a time, a failing book doesn't stop the others. `convert`
+// returns where the annotated book was saved.
+pub fn run_batch<F, E>(
+    batch_id: &str,
+    books: &[PathBuf],
+    concurrency: usize,
+    token: &CancelToken,
+    convert: F,
+    on_event: E
+) -> BatchSummary
+    where F: Fn(&Path) -> Result<PathBuf, String> + Sync, E: Fn(BatchBookEvent) + Sync
+{
+    let next = AtomicUsize::new(0);
+    let finished = AtomicUsize::new(0);
+    let outcomes: Mutex<Vec<Option<Result<PathBuf, String>>>> = Mutex::new(vec![None; books.len()]);
+    let event = |book: &Path, status, finished, result: Option<&Result<PathBuf, String>>| {
+        on_event(BatchBookEvent {
+            batch_id: batch_id.to_string(),
+            book: book.to_string_lossy().into_owned(),
+            status,
+            finished,
+            total: books.len(),
+            output: result.and_then(|result| result.as_ref().ok()).map(|output| output.to_string_lossy().into_owned()),
+            error: result.and_then(|result| result.as_ref().err()).cloned(),
+        })
+    };
+
+    thread::scope(|scope| {
+        for _ in 0..concurrency.max(1).min(books.len()) {
+            scope.spawn(|| {
+                loop {
+                    if token.is_cancelled() {
+                        break;
+                    }
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    let Some(book) = books.get(index) else {
+                        break;
+                    };
+                    event(book, BookStatus::Started, finished.load(Ordering::SeqCst), None);
+                    let result = convert(book);
+                    if result.is_err() && token.is_cancelled() {
+                        // stopped half way, it's one of the books left
+                        event(book, BookStatus::Cancelled, finished.load(Ordering::SeqCst), None);
+                        break;
+                    }
+                    let status = if result.is_ok() { BookStatus::Done } else { BookStatus::Failed };
+                    let done = finished.fetch_add(1, Ordering::SeqCst) + 1;
+                    event(book, status, done, Some(&result));
+                    outcomes.lock().unwrap()[index] = Some(result);
+                }
+            });
+        }
+    });
+
+    let mut summary = BatchSummary { batch_id: batch_id.to_string(), ..Default::default() };
+    for (book, outcome) in books.iter().zip(outcomes.into_inner().unwrap()) {
+        let book = book.to_string_lossy().into_owned();
+        match outcome {
+            Some(Ok(output)) =>
+                summary.succeeded.push(BookOutcome {
+                    book,
+                    output: Some(output.to_string_lossy().into_owned()),
+                    error: None,
+                }),
+            Some(Err(error)) => summary.failed.push(BookOutcome { book, output: None, error: Some(error) }),
+            None => summary.cancelled.push(book),
+        }
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::store::TestDir;
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_collect_books() {
+        let root = TestDir::new("batch");
+        fs::create_dir_all(root.join("series")).unwrap();
+        for file in [
+            "b.epub",
+            "a.MOBI",
+            "a-wordwise.epub",
+            "notes.txt",
+            "series/c.azw3",
+            "series/d.epub",
+        ] {
+            fs::write(root.join(file), "").unwrap();
+        }
+        let names = |books: Vec<PathBuf>| -> Vec<String> {
+            books
+                .iter()
+                .map(|book| book.strip_prefix(&root).unwrap().to_string_lossy().replace('\\', "/"))
+                .collect()
+        };
+
+        let mut request = BatchRequest {
+            paths: vec![root.to_string_lossy().into_owned()],
+            ..Default::default()
+        };
+        assert_eq!(names(request.collect_books().unwrap()), vec!["a-wordwise.epub", "a.MOBI", "b.epub"]);
+
+        request.recursive = true;
+        assert_eq!(names(request.collect_books().unwrap()), vec![
+            "a-wordwise.epub",
+            "a.MOBI",
+            "b.epub",
+            "series/c.azw3",
+            "series/d.epub",
+        ]);
+
+        // a book given by name is taken as it is, and only once
+        request.extensions = vec![".epub".to_string()];
+        request.paths.insert(0, root.join("notes.txt").to_string_lossy().into_owned());
+        request.paths.push(root.join("b.epub").to_string_lossy().into_owned());
+        assert_eq!(names(request.collect_books().unwrap()), vec![
+            "notes.txt",
+            "b.epub",
+            "a-wordwise.epub",
+            "series/d.epub",
+        ]);
+
+        request.paths.push(root.join("missing").to_string_lossy().into_owned());
+        assert!(request.collect_books().is_err());
+    }
+
+    #[test]
+    fn test_skip_outputs() {
+        let books: Vec<PathBuf> = ["a-wordwise.epub", "a.mobi", "b.epub", "c.epub", "c-L3.epub"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        // "{stem}-wordwise.epub", a.mobi was annotated before
+        let output_of = |book: &Path| {
+            let stem = book.file_stem()?.to_string_lossy();
+            Some(PathBuf::from(format!("{}-wordwise.epub", stem)))
+        };
+        assert_eq!(skip_outputs(books.clone(), output_of), &books[1..]);
+
+        // "{stem}-L{level}.{ext}" with level 3, a book saved over itself is left to fail on its own
+        let output_of = |book: &Path| {
+            let stem = book.file_stem()?.to_string_lossy();
+            Some(PathBuf::from(if stem == "b" { "b.epub".to_string() } else { format!("{}-L3.epub", stem) }))
+        };
+        assert_eq!(skip_outputs(books.clone(), output_of), &books[..4]);
+    }
+
+    #[test]
+    fn test_concurrency() {
+        let mut request = BatchRequest::default();
+        assert_eq!(request.concurrency(false), DEFAULT_CONCURRENCY);
+        request.concurrency = 4;
+        assert_eq!(request.concurrency(false), 4);
+        assert_eq!(request.concurrency(true), 1);
+    }
+
+    #[test]
+    fn test_run_batch() {
+        let books: Vec<PathBuf> = ["a.epub", "bad.epub", "c.epub", "d.epub"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        let events = Mutex::new(Vec::new());
+        let summary = run_batch(
+            "1",
+            &books,
+            2,
+            &CancelToken::default(),
+            |book| {
+                if book.to_str() == Some("bad.epub") {
+                    Err("no html".to_string())
+                } else {
+                    Ok(book.with_extension("azw3"))
+                }
+            },
+            |event| events.lock().unwrap().push(event)
+        );
+
+        assert_eq!(summary.succeeded.len(), 3);
+        assert_eq!(summary.succeeded[1].output, Some("c.azw3".to_string()));
+        assert_eq!(summary.failed, vec![BookOutcome {
+            book: "bad.epub".to_string(),
+            output: None,
+            error: Some("no html".to_string()),
+        }]);
+        assert!(summary.cancelled.is_empty());
+
+        let events = events.into_inner().unwrap();
+        assert_eq!(events.len(), 8);
+        assert_eq!(events.iter().filter(|event| event.status == BookStatus::Failed).count(), 1);
+        assert_eq!(events.iter().map(|event| event.finished).max(), Some(4));
+    }
+
+    #[test]
+    fn test_cancel_batch() {
+        let books: Vec<PathBuf> = ["a.epub", "b.epub", "c.epub"].iter().map(PathBuf::from).collect();
+        let token = CancelToken::default();
+        let events = Mutex::new(Vec::new());
+        let summary = run_batch(
+            "1",
+            &books,
+            1,
+            &token,
+            |book| {
+                // cancelled while the second book converts
+                if book.to_str() == Some("b.epub") {
+                    token.cancel();
+                    return Err("killed".to_string());
+                }
+                Ok(book.to_path_buf())
+            },
+            |event| events.lock().unwrap().push(event)
+        );
+        assert_eq!(summary.succeeded.len(), 1);
+        assert!(summary.failed.is_empty());
+        assert_eq!(summary.cancelled, vec!["b.epub", "c.epub"]);
+
+        // the book which was started ends too, c.epub never started
+        let statuses: Vec<(String, BookStatus)> = events
+            .into_inner()
+            .unwrap()
+            .into_iter()
+            .map(|event| (event.book, event.status))
+            .collect();
+        assert_eq!(statuses, vec![
+            ("a.epub".to_string(), BookStatus::Started),
+            ("a.epub".to_string(), BookStatus::Done),
+            ("b.epub".to_string(), BookStatus::Started),
+            ("b.epub".to_string(), BookStatus::Cancelled),
+        ]);
+    }
+}
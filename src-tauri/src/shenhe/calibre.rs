@@ -0,0 +1,202 @@
+use super::cmd::run_command;
+use super::settings::load_settings;
+use serde::Serialize;
+use std::env;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::Wry;
+
+pub const EBOOK_CONVERT: &str = "ebook-convert";
+// the oldest calibre the conversions are known to work with
+pub const MIN_VERSION: (u32, u32, u32) = (5, 0, 0);
+pub const NOT_INSTALLED: &str =
+    "Please install calibre first, click the 💗 on the left to open the About dialog, you can find the download URL there.";
+
+// the ebook-convert found last, looking for it runs every candidate.
+static LOCATED: Mutex<Option<CalibreReport>> = Mutex::new(None);
+
+// where ebook-convert was looked for and what was found.
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct CalibreReport {
+    // the ebook-convert used, or the last one which couldn't be
+    pub path: Option<String>,
+    // "7.4.0", from ebook-convert --version
+    pub version: Option<String>,
+    // why calibre can't be used, None when it can
+    pub reason: Option<String>,
+    pub searched: Vec<String>,
+}
+
+impl CalibreReport {
+    pub fn found(&self) -> bool {
+        self.path.is_some() && self.reason.is_none()
+    }
+}
+
+// the ebook-convert to run, found the first time and kept until `forget` is called.
+pub fn ebook_convert() -> Result<String, String> {
+    let mut located = LOCATED.lock().unwrap();
+    if located.is_none() {
+        let configured = load_settings()
+            .map(|(settings, _)| settings.ebook_convert)
+            .unwrap_or_default();
+        *located = Some(locate(&configured));
+    }
+    let report = located.as_ref().unwrap();
+    match (&report.path, &report.reason) {
+        (Some(path), None) => Ok(path.clone()),
+        (_, Some(reason)) => Err(reason.clone()),
+        (None, None) => Err(NOT_INSTALLED.to_string()),
+    }
+}
+
+// look for ebook-convert again, after the settings changed or calibre was installed.
+pub fn relocate(configured: &str) -> CalibreReport {
+    let report = locate(configured);
+    *LOCATED.lock().unwrap() = Some(report.clone());
+    report
+}
+
+pub fn forget() {
+    *LOCATED.lock().unwrap() = None;
+}
+
+// the configured executable only when there is one, else the first usable one of the PATH and the usual
+// install folders.
+pub fn locate(configured: &str) -> CalibreReport {
+    let candidates = if configured.is_empty() { candidates() } else { vec![PathBuf::from(configured)] };
+    let mut report = CalibreReport {
+        searched: candidates
+            .iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect(),
+        ..Default::default()
+    };
+    for candidate in candidates.iter().filter(|path| path.is_file()) {
+        let path = candidate.to_string_lossy().into_owned();
+        let version = run_command::<Wry>(&path, None, &["--version"]).map(|output| parse_version(&output));
+        report.path = Some(path.clone());
+        report.version = None;
+        report.reason = Some(match version {
+            Err(error) => format!("{} doesn't run: {}", path, error),
+            Ok(None) => format!("{} doesn't look like calibre's ebook-convert", path),
+            Ok(Some(version)) => {
+                report.version = Some(format!("{}.{}.{}", version.0, version.1, version.2));
+                if version >= MIN_VERSION {
+                    report.reason = None;
+                    return report;
+                }
+                format!(
+                    "calibre {}.{}.{} is too old, {}.{}.{} or newer is needed",
+                    version.0,
+                    version.1,
+                    version.2,
+                    MIN_VERSION.0,
+                    MIN_VERSION.1,
+                    MIN_VERSION.2
+                )
+            }
+        });
+    }
+    if report.path.is_none() {
+        report.reason = Some(if configured.is_empty() {
+            NOT_INSTALLED.to_string()
+        } else {
+            format!("{} is not found, please check the ebook-convert path in the settings.", configured)
+        });
+    }
+    report
+}
+
+// the folders of the PATH, then where the calibre installers put it.
+fn candidates() -> Vec<PathBuf> {
+    let executable = format!("{}{}", EBOOK_CONVERT, env::consts::EXE_SUFFIX);
+    let mut candidates: Vec<PathBuf> = env
+        ::var_os("PATH")
+        .map(|path| {
+            env::split_paths(&path)
+                .map(|folder| folder.join(&executable))
+                .collect()
+        })
+        .unwrap_or_default();
+    for folder in install_folders() {
+        let candidate = folder.join(&executable);
+        if !candidates.contains(&candidate) {
+            candidates.push(candidate);
+        }
+    }
+    candidates
+}
+
+#[cfg(target_os = "windows")]
+fn install_folders() -> Vec<PathBuf> {
+    ["ProgramFiles", "ProgramFiles(x86)", "LOCALAPPDATA"]
+        .iter()
+        .filter_map(|variable| env::var_os(variable))
+        .flat_map(|folder| {
+            let folder = PathBuf::from(folder);
+            vec![folder.join("Calibre2"), folder.join("Calibre")]
+        })
+        .collect()
+}
+
+#[cfg(target_os = "macos")]
+fn install_folders() -> Vec<PathBuf> {
+    let mut folders = vec![PathBuf::from("/Applications/calibre.app/Contents/MacOS")];
+    if let Some(home) = env::var_os("HOME") {
+        folders.push(PathBuf::from(home).join("Applications/calibre.app/Contents/MacOS"));
+    }
+    folders
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn install_folders() -> Vec<PathBuf> {
+    let mut folders = vec![PathBuf::from("/opt/calibre"), PathBuf::from("/usr/bin"), PathBuf::from("/usr/local/bin")];
+    if let Some(home) = env::var_os("HOME") {
+        folders.push(PathBuf::from(home).join("calibre-bin/calibre"));
+    }
+    folders
+}
+
+// "ebook-convert (calibre 7.4.0)" gives (7, 4, 0), a missing part is 0.
+fn parse_version(output: &str) -> Option<(u32, u32, u32)> {
+    let start = output.find("calibre ")? + "calibre ".len();
+    let version: Vec<u32> = output[start..]
+        .split(|c: char| !c.is_ascii_digit() && c != '.')
+        .next()?
+        .split('.')
+        .map_while(|part| part.parse().ok())
+        .collect();
+    match version.as_slice() {
+        [] => None,
+        [major] => Some((*major, 0, 0)),
+        [major, minor] => Some((*major, *minor, 0)),
+        [major, minor, patch, ..] => Some((*major, *minor, *patch)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("ebook-convert (calibre 7.4.0)\nCreated by: Kovid Goyal"), Some((7, 4, 0)));
+        assert_eq!(parse_version("ebook-convert.exe (calibre 6.29)"), Some((6, 29, 0)));
+        assert_eq!(parse_version("ebook-convert 1.0"), None);
+        assert!(Some((4, 23, 0)) < Some(MIN_VERSION));
+    }
+
+    #[test]
+    fn test_locate() {
+        let report = locate("/nowhere/ebook-convert");
+        assert!(!report.found());
+        assert_eq!(report.searched, vec!["/nowhere/ebook-convert"]);
+        assert!(report.reason.unwrap().contains("not found"));
+
+        let searched = locate("").searched;
+        assert!(searched.iter().all(|path| Path::new(path).file_stem().unwrap() == EBOOK_CONVERT));
+        assert!(searched.len() >= install_folders().len());
+    }
+}
@@ -0,0 +1,52 @@
+use serde::Serialize;
+
+// what the app was started with: `ebook-wordwise [--preset <name>] [book or folder...]`. the preset's options are
+// selected and the books, if any, annotated with them right away.
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct StartupOptions {
+    pub preset: Option<String>,
+    pub books: Vec<String>,
+}
+
+// the arguments without the program's name. options the app doesn't know are left to the system, macOS adds
+// its own for example.
+pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<StartupOptions, String> {
+    let mut options = StartupOptions::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--preset" {
+            let name = args.next().ok_or("--preset needs the name of a preset.".to_string())?;
+            options.preset = Some(name);
+        } else if let Some(name) = arg.strip_prefix("--preset=") {
+            options.preset = Some(name.to_string());
+        } else if !arg.starts_with('-') {
+            options.books.push(arg);
+        }
+    }
+    if options.preset.as_ref().is_some_and(|name| name.trim().is_empty()) {
+        return Err("--preset needs the name of a preset.".to_string());
+    }
+    Ok(options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse_args(args(&[])).unwrap(), StartupOptions::default());
+        let options = parse_args(args(&["--preset", "kids A2 ruby", "Dune.epub", "-psn_0_1234", "books"]));
+        assert_eq!(options.unwrap(), StartupOptions {
+            preset: Some("kids A2 ruby".to_string()),
+            books: args(&["Dune.epub", "books"]),
+        });
+        assert_eq!(parse_args(args(&["--preset=adult C1"])).unwrap().preset, Some("adult C1".to_string()));
+        assert!(parse_args(args(&["Dune.epub", "--preset"])).is_err());
+        assert!(parse_args(args(&["--preset="])).is_err());
+    }
+}
@@ -1,34 +1,82 @@
 // use std::io::{self, Write};
+use super::calibre::{ EBOOK_CONVERT, NOT_INSTALLED };
+use super::job::CANCELLED;
 use super::types::ProgressReporter;
+use std::io::{ BufRead, BufReader, Read };
+use std::path::Path;
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
-use std::process::Command;
-use tauri::{ Runtime, Wry };
+use std::process::{ Command, Stdio };
+use std::sync::mpsc::{ self, Receiver, RecvTimeoutError, Sender };
+use std::thread;
+use std::time::{ Duration, Instant };
+use tauri::Runtime;
 #[cfg(target_os = "windows")]
 use winapi::um::winbase::CREATE_NO_WINDOW;
 
+// how often a running command looks for a cancelled job.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+// how long the pipes are read once the command is over.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(2);
+
 pub fn run_command<R: Runtime>(
     name: &str,
-    _reporter: Option<&ProgressReporter<R>>,
+    reporter: Option<&ProgressReporter<R>>,
     args: &[&str]
 ) -> Result<String, String> {
     let mut command = Command::new(name);
-    command.args(args);
+    command.args(args).stdout(Stdio::piped()).stderr(Stdio::piped());
     #[cfg(target_os = "windows")]
     {
         command.creation_flags(CREATE_NO_WINDOW);
     }
-    let output = command.output().map_err(|err| {
-        if name == "ebook-convert" {
-            return String::from(
-                "Please install calibre first, click the 💗 on the left to open the About dialog, you can find the download URL there."
-            );
+    let mut child = command.spawn().map_err(|err| {
+        if Path::new(name).file_stem().is_some_and(|stem| stem == EBOOK_CONVERT) {
+            return String::from(NOT_INSTALLED);
         }
         return format!("{}: {}", name, err);
     })?;
 
-    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    // a full pipe would block the child, read them while it runs. the lines come back here, the reporter
+    // stays on this thread.
+    let (sender, receiver) = mpsc::channel();
+    read_lines(child.stdout.take(), Pipe::Stdout, sender.clone());
+    read_lines(child.stderr.take(), Pipe::Stderr, sender);
+    let mut stdout = String::new();
+    let mut stderr = String::new();
+    let mut take_line = |(pipe, line): (Pipe, String)| {
+        if let Some(reporter) = reporter {
+            if let Some(progress) = parse_progress(&line) {
+                reporter.report(progress);
+            }
+            if !line.trim().is_empty() {
+                reporter.log(line.trim_end());
+            }
+        }
+        let output = if pipe == Pipe::Stdout { &mut stdout } else { &mut stderr };
+        output.push_str(&line);
+        output.push('\n');
+    };
+    let status = loop {
+        match receiver.recv_timeout(POLL_INTERVAL) {
+            Ok(line) => take_line(line),
+            Err(RecvTimeoutError::Timeout) => {}
+            // both pipes are closed, the child is about to exit
+            Err(RecvTimeoutError::Disconnected) => thread::sleep(POLL_INTERVAL),
+        }
+        if let Some(status) = child.try_wait().map_err(|err| format!("{}: {}", name, err))? {
+            break status;
+        }
+        if reporter.is_some_and(|reporter| reporter.is_cancelled()) {
+            child.kill().ok();
+            child.wait().ok();
+            // the readers end with the pipes, their last lines don't matter
+            drain(&receiver, None::<&ProgressReporter<R>>, |_| {});
+            return Err(CANCELLED.to_string());
+        }
+    };
+    // what is left in the pipes
+    drain(&receiver, reporter, &mut take_line);
 
     // if !stdout.is_empty() {
     //     io::stdout().write_all(stdout.as_bytes()).unwrap();
@@ -38,16 +86,64 @@ pub fn run_command<R: Runtime>(
     //     io::stderr().write_all(stderr.as_bytes()).unwrap();
     // }
 
-    if output.status.success() {
+    if status.success() {
         Ok(stdout)
     } else {
         Err(stderr)
     }
 }
 
-pub fn ebook_convert_exists() -> bool {
-    let reporter: Option<&ProgressReporter<Wry>> = None;
-    run_command("ebook-convert", reporter, &["--version"]).is_ok()
+// take the lines left until both readers are done. a helper the command started may still hold a pipe
+// open, the readers are left behind after DRAIN_TIMEOUT or when the job is cancelled.
+fn drain<R: Runtime>(
+    receiver: &Receiver<(Pipe, String)>,
+    reporter: Option<&ProgressReporter<R>>,
+    mut take_line: impl FnMut((Pipe, String))
+) {
+    let deadline = Instant::now() + DRAIN_TIMEOUT;
+    while !reporter.is_some_and(|reporter| reporter.is_cancelled()) {
+        let left = deadline.saturating_duration_since(Instant::now());
+        match receiver.recv_timeout(left.min(POLL_INTERVAL)) {
+            Ok(line) => take_line(line),
+            Err(RecvTimeoutError::Timeout) if !left.is_zero() => {}
+            Err(_) => break,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Pipe {
+    Stdout,
+    Stderr,
+}
+
+// send the pipe's lines until it is closed. calibre may end a line with \r, and not always write utf-8.
+fn read_lines<P: Read + Send + 'static>(pipe: Option<P>, kind: Pipe, sender: Sender<(Pipe, String)>) {
+    let Some(pipe) = pipe else {
+        return;
+    };
+    thread::spawn(move || {
+        let mut reader = BufReader::new(pipe);
+        let mut line = Vec::new();
+        while reader.read_until(b'\n', &mut line).is_ok_and(|read| read > 0) {
+            for part in String::from_utf8_lossy(&line).split(['\r', '\n']).filter(|part| !part.is_empty()) {
+                if sender.send((kind, part.to_string())).is_err() {
+                    return;
+                }
+            }
+            line.clear();
+        }
+    });
+}
+
+// calibre's "34% Running transforms on e-book" gives 0.34.
+fn parse_progress(line: &str) -> Option<f32> {
+    let (percent, _) = line.trim_start().split_once('%')?;
+    if percent.is_empty() || !percent.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let percent: f32 = percent.parse().ok()?;
+    (percent <= 100.0).then_some(percent / 100.0)
 }
 
 #[cfg(test)]
@@ -70,8 +166,29 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_progress() {
+        assert_eq!(parse_progress("34% Running transforms on e-book..."), Some(0.34));
+        assert_eq!(parse_progress("  1% Converting input to HTML..."), Some(0.01));
+        assert_eq!(parse_progress("Output saved to   /books/Dune.htmlz"), None);
+        assert_eq!(parse_progress("100% ok"), Some(1.0));
+        assert_eq!(parse_progress("250% no"), None);
+        assert_eq!(parse_progress("% no"), None);
+    }
+
+    // the background sleep keeps stdout open after the shell exits
+    #[cfg(unix)]
+    #[test]
+    fn test_run_command_left_pipe_open() {
+        let reporter: Option<&ProgressReporter<Wry>> = None;
+        let started = Instant::now();
+        let output = run_command("sh", reporter, &["-c", "sleep 10 & echo converted"]).unwrap();
+        assert_eq!(output, "converted\n");
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
     #[test]
     fn test_ebook_convert_exists() {
-        assert!(ebook_convert_exists());
+        assert!(super::super::calibre::locate("").found());
     }
 }
@@ -0,0 +1,225 @@
+use serde::{ Deserialize, Serialize };
+
+// the options of `extra_args`, the ones which only change how the book looks. the others could read or
+// write files, or undo what the typed options do.
+const ALLOWED_ARGS: [&str; 32] = [
+    "--base-font-size",
+    "--font-size-mapping",
+    "--minimum-line-height",
+    "--line-height",
+    "--embed-all-fonts",
+    "--subset-embedded-fonts",
+    "--change-justification",
+    "--smarten-punctuation",
+    "--unsmarten-punctuation",
+    "--remove-paragraph-spacing",
+    "--remove-paragraph-spacing-indent-size",
+    "--insert-blank-line",
+    "--insert-blank-line-size",
+    "--margin-top",
+    "--margin-bottom",
+    "--margin-left",
+    "--margin-right",
+    "--pretty-print",
+    "--disable-font-rescaling",
+    "--linearize-tables",
+    "--no-default-epub-cover",
+    "--epub-flatten",
+    "--epub-inline-toc",
+    "--epub-toc-at-end",
+    "--mobi-keep-original-images",
+    "--mobi-toc-at-start",
+    "--no-inline-toc",
+    "--pdf-page-numbers",
+    "--pdf-add-toc",
+    "--pdf-serif-family",
+    "--pdf-sans-family",
+    "--pdf-mono-family",
+];
+const PAPER_SIZES: [&str; 16] = [
+    "a0",
+    "a1",
+    "a2",
+    "a3",
+    "a4",
+    "a5",
+    "a6",
+    "b0",
+    "b1",
+    "b2",
+    "b3",
+    "b4",
+    "b5",
+    "b6",
+    "legal",
+    "letter",
+];
+
+// calibre's kindle profile doesn't keep <ruby> and most kindles show its text in brackets after the word.
+pub fn is_kindle(format: &str) -> bool {
+    format == "mobi" || format == "azw3"
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MobiFileType {
+    Old,
+    Both,
+    New,
+}
+
+impl MobiFileType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            MobiFileType::Old => "old",
+            MobiFileType::Both => "both",
+            MobiFileType::New => "new",
+        }
+    }
+}
+
+// the options of the last conversion, from html to the annotated book. None leaves calibre's default, or the
+// format's one, and an option of another format is left out.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct ConversionOptions {
+    // the reader the book is made for, "kindle_pw3", "kobo"...
+    pub output_profile: Option<String>,
+    // 2 or 3
+    pub epub_version: Option<u8>,
+    pub mobi_file_type: Option<MobiFileType>,
+    // "a4", "letter"...
+    pub paper_size: Option<String>,
+    pub pdf_default_font_size: Option<u32>,
+    // "--flag" or "--flag=value", each flag has to be in ALLOWED_ARGS
+    pub extra_args: Vec<String>,
+    // a kindle book keeps the "On top" style instead of falling back to the inline one
+    pub keep_ruby: bool,
+}
+
+impl ConversionOptions {
+    // what can't be passed to calibre, empty when everything can.
+    pub fn check(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if let Some(profile) = &self.output_profile {
+            let valid = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-';
+            if profile.is_empty() || !profile.chars().all(valid) {
+                problems.push(format!("unknown output profile \"{}\"", profile));
+            }
+        }
+        if self.epub_version.is_some_and(|version| !(2..=3).contains(&version)) {
+            problems.push(format!("there is no epub version {}", self.epub_version.unwrap()));
+        }
+        if let Some(size) = &self.paper_size {
+            if !PAPER_SIZES.contains(&size.as_str()) {
+                problems.push(format!("unknown paper size \"{}\"", size));
+            }
+        }
+        if self.pdf_default_font_size == Some(0) {
+            problems.push("the pdf font size can't be 0".to_string());
+        }
+        for arg in &self.extra_args {
+            let flag = arg.split('=').next().unwrap_or_default();
+            if !ALLOWED_ARGS.contains(&flag) {
+                problems.push(format!("the conversion option \"{}\" is not allowed", arg));
+            }
+        }
+        problems
+    }
+
+    // the arguments for a book in `format`. a kindle book gets the new mobi format and the kindle profile unless
+    // told otherwise, neither keeps the ruby of the "On top" style.
+    pub fn args(&self, format: &str) -> Result<Vec<String>, String> {
+        let problems = self.check();
+        if !problems.is_empty() {
+            return Err(format!("Conversion options: {}.", problems.join(", ")));
+        }
+        let kindle = is_kindle(format);
+        let mut args = Vec::new();
+        match (&self.output_profile, kindle) {
+            (Some(profile), _) => args.push(format!("--output-profile={}", profile)),
+            (None, true) => args.push("--output-profile=kindle".to_string()),
+            (None, false) => {}
+        }
+        match format {
+            "epub" => {
+                if let Some(version) = self.epub_version {
+                    args.push(format!("--epub-version={}", version));
+                }
+            }
+            "mobi" => {
+                let file_type = self.mobi_file_type.unwrap_or(MobiFileType::New);
+                args.push(format!("--mobi-file-type={}", file_type.as_str()));
+            }
+            "pdf" => {
+                if let Some(size) = &self.paper_size {
+                    args.push(format!("--paper-size={}", size));
+                }
+                if let Some(size) = self.pdf_default_font_size {
+                    args.push(format!("--pdf-default-font-size={}", size));
+                }
+            }
+            _ => {}
+        }
+        args.extend(self.extra_args.iter().cloned());
+        Ok(args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::types::Payload;
+    use super::*;
+
+    #[test]
+    fn test_args() {
+        let options = ConversionOptions::default();
+        assert!(options.args("epub").unwrap().is_empty());
+        assert_eq!(options.args("mobi").unwrap(), vec!["--output-profile=kindle", "--mobi-file-type=new"]);
+        assert_eq!(options.args("azw3").unwrap(), vec!["--output-profile=kindle"]);
+
+        let options = ConversionOptions {
+            output_profile: Some("kindle_pw3".to_string()),
+            epub_version: Some(3),
+            mobi_file_type: Some(MobiFileType::Both),
+            paper_size: Some("a5".to_string()),
+            extra_args: vec!["--embed-all-fonts".to_string(), "--margin-top=10".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            options.args("epub").unwrap(),
+            vec!["--output-profile=kindle_pw3", "--epub-version=3", "--embed-all-fonts", "--margin-top=10"]
+        );
+        assert_eq!(options.args("mobi").unwrap()[1], "--mobi-file-type=both");
+        assert_eq!(options.args("pdf").unwrap()[1], "--paper-size=a5");
+    }
+
+    #[test]
+    fn test_kindle_style() {
+        let mut payload = Payload { format: "azw3".to_string(), wordwise_style: 1, ..Default::default() };
+        assert_eq!(payload.style(), 0);
+        payload.conversion.keep_ruby = true;
+        assert_eq!(payload.style(), 1);
+        let payload = Payload { format: "epub".to_string(), wordwise_style: 1, ..Default::default() };
+        assert_eq!(payload.style(), 1);
+        let payload = Payload { format: "mobi".to_string(), wordwise_style: 2, ..Default::default() };
+        assert_eq!(payload.style(), 2);
+    }
+
+    #[test]
+    fn test_check() {
+        let options = ConversionOptions {
+            output_profile: Some("kindle; rm".to_string()),
+            epub_version: Some(4),
+            paper_size: Some("a9".to_string()),
+            extra_args: vec![
+                "--margin-top=10".to_string(),
+                "--extra-css=/etc/passwd".to_string(),
+                "/tmp/out.epub".to_string()
+            ],
+            ..Default::default()
+        };
+        assert_eq!(options.check().len(), 5);
+        assert!(options.args("epub").is_err());
+    }
+}
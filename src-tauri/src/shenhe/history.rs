@@ -0,0 +1,245 @@
+use super::annotation::resource_files;
+use super::job::{ JobResult, JobStatus };
+use super::store::{ read_json, store_path, write_json };
+use super::types::Payload;
+use once_cell::sync::OnceCell;
+use serde::{ Deserialize, Serialize };
+use std::fs::File;
+use std::io::Read;
+use std::path::{ Path, PathBuf };
+use std::sync::Mutex;
+use std::time::{ SystemTime, UNIX_EPOCH };
+
+// the app's data dir, the history goes to the current dir without it.
+pub static DATA_DIR: OnceCell<String> = OnceCell::new();
+
+const HISTORY_FILE: &str = "history.json";
+// the jobs of a batch finish side by side, one of them writes the history at a time
+static HISTORY_LOCK: Mutex<()> = Mutex::new(());
+
+// a file a job depended on, the same checksum means the same file.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FileVersion {
+    pub file: String,
+    pub checksum: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HistoryEntry {
+    pub id: String,
+    // seconds since the unix epoch
+    pub started: u64,
+    pub duration_ms: u64,
+    pub book: String,
+    // None when the book couldn't be read
+    pub checksum: Option<String>,
+    // the settings the book was annotated with, re-running the job uses them again
+    pub payload: Payload,
+    // the dictionary, lemmatization and frequency files
+    pub resources: Vec<FileVersion>,
+    pub output: Option<String>,
+    pub status: JobStatus,
+    pub message: String,
+}
+
+impl HistoryEntry {
+    pub fn new(payload: &Payload, job: &JobResult, output: Option<&Path>, started: SystemTime) -> Self {
+        let resources = resource_files(&payload.book_language, &payload.language)
+            .iter()
+            .filter_map(|path| {
+                checksum_file(path)
+                    .ok()
+                    .map(|checksum| FileVersion {
+                        file: path.file_name().unwrap().to_string_lossy().into_owned(),
+                        checksum,
+                    })
+            })
+            .collect();
+
+        HistoryEntry {
+            id: job.job_id.clone(),
+            started: started
+                .duration_since(UNIX_EPOCH)
+                .map(|since| since.as_secs())
+                .unwrap_or_default(),
+            duration_ms: started
+                .elapsed()
+                .map(|elapsed| elapsed.as_millis() as u64)
+                .unwrap_or_default(),
+            book: payload.book.clone(),
+            checksum: checksum_file(Path::new(&payload.book)).ok(),
+            payload: payload.clone(),
+            resources,
+            output: output.map(|output| output.to_string_lossy().into_owned()),
+            status: job.status,
+            message: job.message.clone(),
+        }
+    }
+}
+
+// every field is optional, an empty filter lists the whole history.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct HistoryFilter {
+    // a part of the book's path, whatever the case
+    #[serde(default)]
+    pub book: String,
+    #[serde(default)]
+    pub status: Option<JobStatus>,
+    // seconds since the unix epoch
+    #[serde(default)]
+    pub since: Option<u64>,
+    #[serde(default)]
+    pub until: Option<u64>,
+}
+
+impl HistoryFilter {
+    fn matches(&self, entry: &HistoryEntry) -> bool {
+        entry.book.to_lowercase().contains(&self.book.to_lowercase()) &&
+            (self.status.is_none() || self.status == Some(entry.status)) &&
+            self.since.unwrap_or(0) <= entry.started &&
+            entry.started <= self.until.unwrap_or(u64::MAX)
+    }
+}
+
+// the jobs run so far, kept in a json file.
+pub struct History {
+    path: PathBuf,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        History::new(store_path(&DATA_DIR, HISTORY_FILE))
+    }
+}
+
+impl History {
+    pub fn new(path: PathBuf) -> Self {
+        History { path }
+    }
+
+    // the newest first.
+    pub fn list(&self, filter: &HistoryFilter) -> Result<Vec<HistoryEntry>, String> {
+        let _lock = HISTORY_LOCK.lock().unwrap();
+        let mut entries: Vec<HistoryEntry> = self
+            .load()?
+            .into_iter()
+            .filter(|entry| filter.matches(entry))
+            .collect();
+        entries.reverse();
+        Ok(entries)
+    }
+
+    pub fn get(&self, id: &str) -> Result<Option<HistoryEntry>, String> {
+        let _lock = HISTORY_LOCK.lock().unwrap();
+        Ok(self.load()?.into_iter().find(|entry| entry.id == id))
+    }
+
+    pub fn add(&self, entry: HistoryEntry) -> Result<(), String> {
+        let _lock = HISTORY_LOCK.lock().unwrap();
+        let mut entries = self.load()?;
+        entries.push(entry);
+        self.save(&entries)
+    }
+
+    // returns how many entries were deleted.
+    pub fn delete(&self, ids: &[String]) -> Result<usize, String> {
+        let _lock = HISTORY_LOCK.lock().unwrap();
+        let mut entries = self.load()?;
+        let count = entries.len();
+        entries.retain(|entry| !ids.contains(&entry.id));
+        let deleted = count - entries.len();
+        if deleted > 0 {
+            self.save(&entries)?;
+        }
+        Ok(deleted)
+    }
+
+    fn load(&self) -> Result<Vec<HistoryEntry>, String> {
+        Ok(read_json(&self.path)?.unwrap_or_default())
+    }
+
+    fn save(&self, entries: &[HistoryEntry]) -> Result<(), String> {
+        write_json(&self.path, entries)
+    }
+}
+
+// the 64 bit FNV-1a hash of the file, in hex. it tells a changed book or dictionary, it isn't meant for security.
+pub fn checksum_file(path: &Path) -> Result<String, String> {
+    let mut file = File::open(path).map_err(|e| e.to_string())?;
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let mut buffer = [0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer).map_err(|e| e.to_string())?;
+        if read == 0 {
+            break;
+        }
+        for byte in &buffer[..read] {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    Ok(format!("{:016x}", hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::job::CancelToken;
+    use super::super::store::TestDir;
+    use std::fs;
+
+    fn payload(book: &str) -> Payload {
+        serde_json::from_str(
+            &format!(
+                r#"{{"book":"{}","format":"epub","language":"en","hint_level":3,"allow_long":false,"show_phoneme":false,"wordwise_style":0}}"#,
+                book
+            )
+        ).unwrap()
+    }
+
+    #[test]
+    fn test_history() {
+        let root = TestDir::new("history");
+        let history = History::new(root.join(HISTORY_FILE));
+        assert!(history.list(&HistoryFilter::default()).unwrap().is_empty());
+
+        let token = CancelToken::default();
+        let started = SystemTime::now();
+        let done = JobResult::new("1", Ok("saved".to_string()), &token);
+        let failed = JobResult::new("2", Err("no html".to_string()), &token);
+        let output = Path::new("Dune-wordwise.epub");
+        history.add(HistoryEntry::new(&payload("Dune.epub"), &done, Some(output), started)).unwrap();
+        history.add(HistoryEntry::new(&payload("Emma.mobi"), &failed, None, started)).unwrap();
+
+        let entries = history.list(&HistoryFilter::default()).unwrap();
+        assert_eq!(entries.iter().map(|entry| entry.id.as_str()).collect::<Vec<_>>(), vec!["2", "1"]);
+        assert_eq!(entries[1].output, Some("Dune-wordwise.epub".to_string()));
+        assert_eq!(entries[1].payload.hint_level, 3);
+        // the books don't exist
+        assert_eq!(entries[1].checksum, None);
+
+        let filter = HistoryFilter { book: "dune".to_string(), ..Default::default() };
+        assert_eq!(history.list(&filter).unwrap().len(), 1);
+        let filter = HistoryFilter { status: Some(JobStatus::Failed), ..Default::default() };
+        assert_eq!(history.list(&filter).unwrap()[0].message, "no html");
+        let filter = HistoryFilter { since: Some(entries[0].started + 1), ..Default::default() };
+        assert!(history.list(&filter).unwrap().is_empty());
+
+        assert_eq!(history.get("2").unwrap().unwrap().book, "Emma.mobi");
+        assert_eq!(history.delete(&["2".to_string(), "3".to_string()]).unwrap(), 1);
+        assert!(history.get("2").unwrap().is_none());
+        assert_eq!(history.list(&HistoryFilter::default()).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_checksum_file() {
+        let root = TestDir::new("checksum");
+        fs::create_dir_all(&root).unwrap();
+        let path = root.join("a.txt");
+        fs::write(&path, "a").unwrap();
+        // the FNV-1a reference value of "a"
+        assert_eq!(checksum_file(&path).unwrap(), "af63dc4c8601ec8c");
+        fs::remove_file(&path).ok();
+        assert!(checksum_file(&path).is_err());
+    }
+}
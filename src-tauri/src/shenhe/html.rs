@@ -1,6 +1,9 @@
 use super::annotation::annotate_phrase;
+use super::occurrence::{ escape_markers, resolve_occurrences };
 use super::types::{ ChunkParameter, ProcessChunkFn, ProgressReporter };
 use rayon::prelude::*;
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::io::{ Read, Seek, SeekFrom, Write };
 use std::sync::{ atomic::{ AtomicUsize, Ordering }, Arc };
 use tauri::Runtime;
@@ -10,12 +13,21 @@ pub fn process_html<R: Read + Seek, W: Write, Rt: Runtime>(
     writer: &mut W,
     param: &ChunkParameter,
     process_fn: ProcessChunkFn,
-    reporter: Option<&ProgressReporter<Rt>>
+    reporter: Option<&ProgressReporter<Rt>>,
+    // the annotations of the books before, for a series
+    seen: &mut HashMap<String, usize>
 ) -> Result<(), String> {
     const CHUNK_SIZE: usize = 100 * 1024; // 100 KB
-    let chunks = split_html(reader, CHUNK_SIZE, param, process_fn, reporter).map_err(|err|
+    let mut chunks = split_html(reader, CHUNK_SIZE, param, process_fn, reporter).map_err(|err|
         err.to_string()
     )?;
+    // the chunks left when the job was cancelled weren't annotated
+    if let Some(reporter) = reporter {
+        reporter.check_cancelled()?;
+    }
+    if param.limits_occurrences() {
+        resolve_occurrences(&mut chunks, param.occurrence_limit, param.density_limit, param.fade, seen);
+    }
 
     // Write the processed chunks back in order
     for chunk in chunks {
@@ -30,7 +42,7 @@ pub fn process_text_fn(input: &str, param: &ChunkParameter) -> String {
         return input.to_string();
     }
 
-    let res = annotate_phrase(param.annotator, input, param.dict, param.lemma, param.def_length);
+    let res = annotate_phrase(input, param);
     res
 }
 
@@ -45,6 +57,13 @@ fn split_html<R: Read + Seek, Rt: Runtime>(
     reader.seek(SeekFrom::Start(0)).map_err(|err| err.to_string())?;
     let mut buffer = vec![0; file_size as usize];
     reader.read_exact(&mut buffer).map_err(|err| format!("{:?}", err))?;
+    // the book's own private use characters would be taken for the markers of the occurrences
+    if param.limits_occurrences() {
+        if let Cow::Owned(escaped) = escape_markers(&String::from_utf8_lossy(&buffer)) {
+            buffer = escaped.into_bytes();
+        }
+    }
+    let file_size = buffer.len();
 
     let body_position = buffer
         .windows("<body".len())
@@ -72,8 +91,8 @@ fn split_html<R: Read + Seek, Rt: Runtime>(
         chunks.push(String::from_utf8_lossy(&buffer[..body_position]).into_owned());
     }
 
-    if body_position < (file_size as usize) {
-        if body_end_position < (file_size as usize) {
+    if body_position < file_size {
+        if body_end_position < file_size {
             let body_chunks = split_chunk(&buffer[body_position..body_end_position], max_size);
             let num_chunks = body_chunks.len();
 
@@ -81,6 +100,9 @@ fn split_html<R: Read + Seek, Rt: Runtime>(
             let new_chunks: Vec<String> = body_chunks
                 .par_iter()
                 .map(|x| {
+                    if reporter.is_some_and(|reporter| reporter.is_cancelled()) {
+                        return x.clone();
+                    }
                     let progress = progress_counter.fetch_add(1, Ordering::SeqCst) + 1;
 
                     if let Some(reporter) = reporter {
@@ -218,8 +240,10 @@ fn chunk_end_with(chunk: &[u8], str: &str) -> bool {
 
 #[cfg(test)]
 mod tests {
-    use super::super::types::{ Annotator, DictRecord, ProgressReporter };
-    use super::{ process_html, ChunkParameter };
+    use super::super::occurrence::OccurrenceLimit;
+    use super::super::phrase::{ PhraseMatcher, DEFAULT_MAX_PHRASE_LENGTH };
+    use super::super::types::{ test_param, Annotator, DictRecord, Difficulty, ProgressReporter };
+    use super::{ process_html, process_text_fn, ChunkParameter };
     use std::collections::HashMap;
     use std::io::Cursor;
     use tauri::Wry;
@@ -255,6 +279,7 @@ mod tests {
             short_def: "xiaoxiao".to_string(),
             example_sentences: "".to_string(),
             hint_lvl: 3,
+            ..Default::default()
         };
         dict.insert("world".to_string(), dr);
 
@@ -265,24 +290,57 @@ mod tests {
             short_def: "fare".to_string(),
             example_sentences: "".to_string(),
             hint_lvl: 3,
+            ..Default::default()
         };
         dict.insert("world".to_string(), dr);
-        let annotator = Annotator::InlineAnnotator(3, false);
-        let param: ChunkParameter = ChunkParameter {
-            dict: &dict,
-            lemma: &lemma,
-            def_length: 1,
-            annotator: &annotator,
-        };
+        let annotator = Annotator::InlineAnnotator(Difficulty::HintLevel(3), false);
+        let matcher = PhraseMatcher::new(&dict, DEFAULT_MAX_PHRASE_LENGTH);
+        let param = test_param(&dict, &lemma, &matcher, &annotator);
 
         for (input, expected) in data {
             let mut reader = Cursor::new(input);
             let mut writer = Cursor::new(Vec::new());
             let reporter: Option<&ProgressReporter<Wry>> = None;
-            process_html(&mut reader, &mut writer, &param, fake_process_text, reporter).unwrap();
+            process_html(
+                &mut reader,
+                &mut writer,
+                &param,
+                fake_process_text,
+                reporter,
+                &mut HashMap::new()
+            ).unwrap();
             let vec_w = writer.into_inner();
             let output_data = String::from_utf8(vec_w).unwrap();
             assert_eq!(output_data, expected);
         }
     }
+
+    #[test]
+    fn test_private_use_characters() {
+        let mut dict = HashMap::new();
+        dict.insert("sociable".to_string(), DictRecord {
+            word: "sociable".to_string(),
+            short_def: "friendly".to_string(),
+            hint_lvl: 1,
+            ..Default::default()
+        });
+        let lemma = HashMap::new();
+        let annotator = Annotator::RubyAnnotator(Difficulty::HintLevel(1), false);
+        let matcher = PhraseMatcher::new(&dict, DEFAULT_MAX_PHRASE_LENGTH);
+        let param = ChunkParameter {
+            occurrence_limit: OccurrenceLimit { max: 1, per_chapter: false },
+            ..test_param(&dict, &lemma, &matcher, &annotator)
+        };
+
+        // the book's own \u{E000} looks like the start of an occurrence
+        let input = "<html><body><p>a \u{E000} sociable \u{E002}sociable\u{E001} friend</p></body></html>";
+        let mut reader = Cursor::new(input);
+        let mut writer = Cursor::new(Vec::new());
+        let reporter: Option<&ProgressReporter<Wry>> = None;
+        process_html(&mut reader, &mut writer, &param, process_text_fn, reporter, &mut HashMap::new()).unwrap();
+        assert_eq!(
+            String::from_utf8(writer.into_inner()).unwrap(),
+            "<html><body><p>a &#xE000; <ruby>sociable<rt>friendly</rt></ruby> &#xE002;sociable&#xE001; friend</p></body></html>"
+        );
+    }
 }
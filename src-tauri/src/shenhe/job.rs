@@ -0,0 +1,121 @@
+use serde::{ Deserialize, Serialize };
+use std::collections::HashMap;
+use std::sync::{ atomic::{ AtomicBool, Ordering }, Arc, Mutex };
+use uuid::Uuid;
+
+pub const CANCELLED: &str = "The job was cancelled.";
+
+// asks a running job to stop, the job checks it between its steps and kills the converter it waits for.
+#[derive(Clone, Default, Debug)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    pub fn check(&self) -> Result<(), String> {
+        if self.is_cancelled() { Err(CANCELLED.to_string()) } else { Ok(()) }
+    }
+}
+
+// the running jobs by id, shared between the commands and the threads doing the work.
+#[derive(Clone, Default)]
+pub struct Jobs(Arc<Mutex<HashMap<String, CancelToken>>>);
+
+impl Jobs {
+    pub fn start(&self) -> (String, CancelToken) {
+        let job_id = Uuid::new_v4().to_string();
+        let token = CancelToken::default();
+        self.0.lock().unwrap().insert(job_id.clone(), token.clone());
+        (job_id, token)
+    }
+
+    // false when the job isn't running, it may have just finished
+    pub fn cancel(&self, job_id: &str) -> bool {
+        match self.0.lock().unwrap().get(job_id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn finish(&self, job_id: &str) {
+        self.0.lock().unwrap().remove(job_id);
+    }
+
+    pub fn is_running(&self, job_id: &str) -> bool {
+        self.0.lock().unwrap().contains_key(job_id)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Done,
+    Failed,
+    Cancelled,
+}
+
+// sent with "event-job" when a job ends, however it ends.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct JobResult {
+    pub job_id: String,
+    pub status: JobStatus,
+    // where the book was saved, or why it wasn't
+    pub message: String,
+}
+
+impl JobResult {
+    pub fn new(job_id: &str, outcome: Result<String, String>, token: &CancelToken) -> Self {
+        let (status, message) = match outcome {
+            Ok(message) => (JobStatus::Done, message),
+            // whatever failed on the way, the job failed because it was stopped
+            Err(_) if token.is_cancelled() => (JobStatus::Cancelled, CANCELLED.to_string()),
+            Err(error) => (JobStatus::Failed, error),
+        };
+        JobResult { job_id: job_id.to_string(), status, message }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jobs() {
+        let jobs = Jobs::default();
+        let (job_id, token) = jobs.start();
+        assert!(jobs.is_running(&job_id));
+        assert!(token.check().is_ok());
+
+        assert!(jobs.cancel(&job_id));
+        assert!(token.is_cancelled());
+        assert_eq!(token.check(), Err(CANCELLED.to_string()));
+
+        jobs.finish(&job_id);
+        assert!(!jobs.is_running(&job_id));
+        assert!(!jobs.cancel(&job_id));
+    }
+
+    #[test]
+    fn test_job_result() {
+        let token = CancelToken::default();
+        let done = JobResult::new("1", Ok("book.epub".to_string()), &token);
+        assert_eq!(done.status, JobStatus::Done);
+        assert_eq!(JobResult::new("1", Err("no html".to_string()), &token).status, JobStatus::Failed);
+
+        // the converter killed on cancel fails with its own error
+        token.cancel();
+        let cancelled = JobResult::new("1", Err("killed".to_string()), &token);
+        assert_eq!(cancelled.status, JobStatus::Cancelled);
+        assert_eq!(cancelled.message, CANCELLED);
+        assert_eq!(serde_json::to_value(&cancelled).unwrap()["status"], "cancelled");
+    }
+}
@@ -1,50 +1,105 @@
 pub mod annotation;
+pub mod batch;
+pub mod calibre;
+pub mod cli;
 pub mod cmd;
+pub mod conversion;
+pub mod history;
 pub mod html;
+pub mod job;
+pub mod morphology;
+pub mod names;
+pub mod occurrence;
+pub mod output;
+pub mod phrase;
+pub mod placement;
+pub mod pos;
+pub mod presets;
+pub mod progression;
+pub mod report;
+pub mod segment;
+pub mod settings;
+pub mod store;
+pub mod tokenize;
 pub mod types;
-use annotation::{ load_dict, load_lemma };
+pub mod workspace;
+use annotation::{ load_dict, load_frequency, load_lemma, rank_by_frequency };
 use html::process_html;
+use phrase::PhraseMatcher;
+use progression::SeriesProgress;
+use segment::Segmenter;
+use std::collections::{ HashMap, HashSet };
 use std::fs::File;
-use std::io::{ BufReader, BufWriter };
+use std::io::{ BufReader, BufWriter, Write };
 use std::path::Path;
 use tauri::Runtime;
-use types::{ Annotator, ChunkParameter, ProgressReporter, WorkMesg };
+use types::{ Annotator, ChunkParameter, DictRecord, Difficulty, Payload, ProgressReporter, WorkMesg };
+
+// the lemmas of a language and its dictionary
+type Dictionaries = (HashMap<String, String>, HashMap<String, DictRecord>);
+
+// the lemmas and the dictionary of the book's language, ranked by the frequency list when the difficulty needs it.
+pub fn load_dictionaries(payload: &Payload) -> Result<Dictionaries, String> {
+    let language = payload.language.as_str();
+    let book_language = payload.book_language.as_str();
+    let lemma = load_lemma(book_language).map_err(|err|
+        format!("lemmatization-{}: {}", book_language, err)
+    )?;
+    let mut dict = load_dict(book_language, language).map_err(|err|
+        format!("dictionary-{}-{}: {}", book_language, language, err)
+    )?;
+    if let Difficulty::OutsideTop(_) = payload.difficulty() {
+        let ranks = load_frequency(book_language).map_err(|err|
+            format!("frequency-{}: {}", book_language, err)
+        )?;
+        rank_by_frequency(&mut dict, &ranks);
+    }
+    Ok((lemma, dict))
+}
 
 pub fn process<R: Runtime>(
     file: &str,
-    language: &str,
-    include_phoneme: bool,
-    def_len: i32,
-    hint_level: i32,
-    wordwise_style: i32,
+    payload: &Payload,
+    excluded: &HashSet<String>,
+    series: &mut SeriesProgress,
     reporter: Option<&ProgressReporter<R>>
 ) -> Result<(), String> {
     //println!("book format: {}", book_format);
-    let lemma = load_lemma().map_err(|err| format!("lemmatization: {}", err))?;
-    let dict = load_dict(language).map_err(|err| format!("dictionary-{}: {}", language, err))?;
-    let annotator = match wordwise_style {
-        0 => Annotator::InlineAnnotator(hint_level, include_phoneme),
-        1 => Annotator::RubyAnnotator(hint_level, include_phoneme),
-        2 => Annotator::ColorAnnotator("red", hint_level, include_phoneme),
-        _ => Annotator::InlineAnnotator(hint_level, include_phoneme),
+    let book_language = payload.book_language.as_str();
+    let include_phoneme = payload.show_phoneme;
+    let def_len = if payload.allow_long { 2 } else { 1 };
+    let difficulty = payload.difficulty();
+    let (lemma, dict) = load_dictionaries(payload)?;
+    let annotator = match payload.style() {
+        0 => Annotator::InlineAnnotator(difficulty, include_phoneme),
+        1 => Annotator::RubyAnnotator(difficulty, include_phoneme),
+        2 => Annotator::ColorAnnotator(&payload.annotation_color, difficulty, include_phoneme),
+        _ => Annotator::InlineAnnotator(difficulty, include_phoneme),
     };
 
+    let matcher = PhraseMatcher::new(&dict, payload.max_phrase_length);
+    let segmenter = Segmenter::new(book_language, &dict);
+    // a series goes on from the books read before
+    let fade = payload.fade(series.books_done);
+
     let param: ChunkParameter = ChunkParameter {
         dict: &dict,
         lemma: &lemma,
+        matcher: &matcher,
+        segmenter: &segmenter,
+        excluded,
+        book_language,
         def_length: def_len,
+        occurrence_limit: payload.occurrence_limit(),
+        density_limit: payload.density_limit(),
+        fade,
         annotator: &annotator,
     };
 
     let f = Path::new(file);
     let out_file = f.file_stem().unwrap().to_str().unwrap();
     let out_file_ext = f.extension().unwrap().to_str().unwrap();
-    let out_file = format!(
-        "{}\\{}.out.{}",
-        f.parent().unwrap().to_str().unwrap(),
-        out_file,
-        out_file_ext
-    );
+    let out_file = f.with_file_name(format!("{}.out.{}", out_file, out_file_ext));
 
     let input = File::open(file).unwrap();
     let mut reader = BufReader::new(input);
@@ -59,14 +114,19 @@ pub fn process<R: Runtime>(
             .map_err(|e| e.to_string())?;
     }
 
-    process_html(&mut reader, &mut writer, &param, html::process_text_fn, reporter).map_err(|err|
-        err.to_string()
-    )?;
+    process_html(
+        &mut reader,
+        &mut writer,
+        &param,
+        html::process_text_fn,
+        reporter,
+        &mut series.seen
+    ).map_err(|err| err.to_string())?;
 
-    // remove the source file
-    std::fs::remove_file(file).map_err(|err| err.to_string())?;
-    // replace the source file with new file
-    std::fs::rename((&out_file).as_str(), file).map_err(|err| err.to_string())?;
+    writer.flush().map_err(|err| err.to_string())?;
+    drop(writer);
+    // replace the source file with new file, rename overwrites it in one step
+    std::fs::rename(&out_file, file).map_err(|err| err.to_string())?;
 
     Ok(())
 }
@@ -0,0 +1,334 @@
+// rule based english morphology, used when the lemmatization table doesn't know a word.
+// it only guesses, every candidate has to be validated against the dictionary by the caller.
+
+// the normal forms `word` may be inflected from, most likely first. `word` is expected to be lowercase.
+pub fn candidate_lemmas(word: &str) -> Vec<String> {
+    let mut candidates = Vec::new();
+    if word.len() < 3 || !word.chars().all(|c| c.is_ascii_lowercase()) {
+        return candidates;
+    }
+
+    candidates.extend(british_variants(word));
+    for stem in inflection_stems(word) {
+        let variants = british_variants(&stem);
+        candidates.push(stem);
+        candidates.extend(variants);
+    }
+
+    let mut seen = Vec::with_capacity(candidates.len());
+    candidates.retain(|candidate| {
+        if candidate.len() < 2 || candidate == word || seen.contains(candidate) {
+            return false;
+        }
+        seen.push(candidate.clone());
+        true
+    });
+    candidates
+}
+
+fn inflection_stems(word: &str) -> Vec<String> {
+    let mut stems = Vec::new();
+
+    // plural nouns and third person verbs
+    if let Some(stem) = word.strip_suffix("ies") {
+        stems.push(format!("{}y", stem));
+        stems.push(format!("{}ie", stem));
+    } else if let Some(stem) = word.strip_suffix("ves") {
+        stems.push(format!("{}f", stem));
+        stems.push(format!("{}fe", stem));
+        stems.push(format!("{}ve", stem));
+    } else if let Some(stem) = word.strip_suffix("es") {
+        if ["s", "x", "z", "ch", "sh", "o"].iter().any(|end| stem.ends_with(end)) {
+            stems.push(stem.to_string());
+        }
+        stems.push(format!("{}e", stem));
+    } else if let Some(stem) = word.strip_suffix('s') {
+        if !stem.ends_with('s') && !stem.ends_with('u') && !stem.ends_with('i') {
+            stems.push(stem.to_string());
+        }
+    }
+
+    // past tense and past participle
+    if let Some(stem) = word.strip_suffix("ied") {
+        stems.push(format!("{}y", stem));
+    } else if let Some(stem) = word.strip_suffix("ed") {
+        stems.extend(suffix_stems(stem));
+    }
+
+    // present participle
+    if let Some(stem) = word.strip_suffix("ying") {
+        stems.push(format!("{}ie", stem));
+    }
+    if let Some(stem) = word.strip_suffix("ing") {
+        stems.extend(suffix_stems(stem));
+    }
+
+    // comparative and superlative
+    if let Some(stem) = word.strip_suffix("ier").or_else(|| word.strip_suffix("iest")) {
+        stems.push(format!("{}y", stem));
+    } else if let Some(stem) = word.strip_suffix("er").or_else(|| word.strip_suffix("est")) {
+        stems.extend(suffix_stems(stem));
+    }
+
+    // adverbs to adjectives, early isn't ear and belly isn't bell
+    if !NOT_ADVERBS.contains(&word) {
+        if let Some(stem) = word.strip_suffix("ically") {
+            stems.push(format!("{}ic", stem));
+        }
+        if let Some(stem) = word.strip_suffix("ily") {
+            stems.push(format!("{}y", stem));
+        } else if let Some(stem) = word.strip_suffix("lly") {
+            // fully -> full, but also naturally -> natural
+            stems.push(format!("{}ll", stem));
+            stems.push(format!("{}l", stem));
+        } else if let Some(stem) = word.strip_suffix("ly").filter(|stem| stem.len() >= 3) {
+            stems.push(stem.to_string());
+            // gently -> gentle, truly -> true
+            stems.push(format!("{}le", stem));
+            stems.push(format!("{}e", stem));
+        }
+    }
+
+    stems.retain(|stem| stem.len() >= 2);
+    stems
+}
+
+// words ending in -ly which aren't the adverb of what is left without it. the adjectives made from a noun,
+// "friendly", are in here too, their meaning isn't the noun's.
+const NOT_ADVERBS: [&str; 50] = [
+    "ally",
+    "anomaly",
+    "apply",
+    "assembly",
+    "belly",
+    "bully",
+    "burly",
+    "butterfly",
+    "chilly",
+    "comply",
+    "costly",
+    "cowardly",
+    "curly",
+    "deadly",
+    "dolly",
+    "dragonfly",
+    "early",
+    "elderly",
+    "family",
+    "firefly",
+    "folly",
+    "friendly",
+    "gully",
+    "hilly",
+    "holly",
+    "homily",
+    "imply",
+    "jelly",
+    "jolly",
+    "likely",
+    "lily",
+    "lively",
+    "lovely",
+    "melancholy",
+    "monopoly",
+    "multiply",
+    "orderly",
+    "rally",
+    "reply",
+    "sally",
+    "silly",
+    "sully",
+    "supply",
+    "surly",
+    "tally",
+    "timely",
+    "underbelly",
+    "unlikely",
+    "wily",
+    "worldly",
+];
+
+// the stems left over once -ed, -ing, -er or -est is removed: a doubled final consonant ("stopped"),
+// a dropped silent e ("hoped"), or nothing at all ("played").
+fn suffix_stems(stem: &str) -> Vec<String> {
+    let mut stems = Vec::new();
+    if stem.len() < 2 {
+        return stems;
+    }
+
+    let bytes = stem.as_bytes();
+    let last = bytes[bytes.len() - 1];
+    let doubled = bytes[bytes.len() - 2] == last && !is_vowel(last);
+    if doubled {
+        let undoubled = stem[..stem.len() - 1].to_string();
+        // calling -> call, but travelled -> travel
+        if matches!(last, b'l' | b's' | b'f' | b'z') {
+            stems.push(stem.to_string());
+            stems.push(undoubled);
+        } else {
+            stems.push(undoubled);
+            stems.push(stem.to_string());
+        }
+        return stems;
+    }
+
+    // a word ending consonant-vowel-consonant would have doubled the consonant, so the e was dropped
+    if ends_with_cvc(bytes) {
+        stems.push(format!("{}e", stem));
+        stems.push(stem.to_string());
+    } else {
+        stems.push(stem.to_string());
+        stems.push(format!("{}e", stem));
+    }
+    stems
+}
+
+fn ends_with_cvc(bytes: &[u8]) -> bool {
+    let n = bytes.len();
+    let last = bytes[n - 1];
+    if is_vowel(last) || matches!(last, b'w' | b'x' | b'y') {
+        return false;
+    }
+    if n == 2 {
+        return is_vowel(bytes[0]);
+    }
+    is_vowel(bytes[n - 2]) && !is_vowel(bytes[n - 3])
+}
+
+fn is_vowel(c: u8) -> bool {
+    matches!(c, b'a' | b'e' | b'i' | b'o' | b'u')
+}
+
+// american spellings of a british word, the dictionaries are written in american english.
+fn british_variants(word: &str) -> Vec<String> {
+    const SPELLINGS: [(&str, &str); 8] = [
+        ("isation", "ization"),
+        ("ise", "ize"),
+        ("yse", "yze"),
+        ("our", "or"),
+        ("tre", "ter"),
+        ("ogue", "og"),
+        ("ence", "ense"),
+        ("ae", "e"),
+    ];
+
+    SPELLINGS.iter()
+        .filter_map(|(british, american)| {
+            word.strip_suffix(british).map(|stem| format!("{}{}", stem, american))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_candidate_lemmas() {
+        let dict: HashSet<&str> = [
+            "fox",
+            "city",
+            "knife",
+            "wolf",
+            "tie",
+            "stop",
+            "hope",
+            "hop",
+            "play",
+            "agree",
+            "carry",
+            "die",
+            "sing",
+            "singe",
+            "big",
+            "large",
+            "happy",
+            "quick",
+            "gentle",
+            "true",
+            "full",
+            "basic",
+            "travel",
+            "call",
+            "color",
+            "center",
+            "realize",
+            "analyze",
+            "organization",
+            "defense",
+            "catalog",
+            "use",
+            "sad",
+            "ear",
+            "bell",
+            "rep",
+            "sill",
+            "on",
+            "app",
+            "assemble",
+            "like",
+            "friend",
+            "ho",
+        ]
+            .into_iter()
+            .collect();
+
+        let corpus = [
+            ("foxes", "fox"),
+            ("cities", "city"),
+            ("knives", "knife"),
+            ("wolves", "wolf"),
+            ("ties", "tie"),
+            ("stopped", "stop"),
+            ("stopping", "stop"),
+            ("hoped", "hope"),
+            ("hoping", "hope"),
+            ("hopped", "hop"),
+            ("played", "play"),
+            ("agreed", "agree"),
+            ("carried", "carry"),
+            ("carries", "carry"),
+            ("dying", "die"),
+            ("singing", "sing"),
+            ("bigger", "big"),
+            ("biggest", "big"),
+            ("larger", "large"),
+            ("happier", "happy"),
+            ("happily", "happy"),
+            ("quickly", "quick"),
+            ("gently", "gentle"),
+            ("truly", "true"),
+            ("fully", "full"),
+            ("basically", "basic"),
+            ("travelled", "travel"),
+            ("travelling", "travel"),
+            ("calling", "call"),
+            ("colours", "color"),
+            ("centre", "center"),
+            ("realised", "realize"),
+            ("realising", "realize"),
+            ("analysed", "analyze"),
+            ("organisation", "organization"),
+            ("defence", "defense"),
+            ("catalogue", "catalog"),
+            ("used", "use"),
+            ("sadly", "sad"),
+        ];
+
+        for (form, expected) in corpus {
+            let lemma = candidate_lemmas(form)
+                .into_iter()
+                .find(|candidate| dict.contains(candidate.as_str()));
+            assert_eq!(lemma.as_deref(), Some(expected), "{}", form);
+        }
+
+        let not_adverbs = ["early", "belly", "reply", "silly", "only", "apply", "assembly", "likely", "friendly", "holy"];
+        for form in ["bus", "this", "sociable", "naïve", "is"].into_iter().chain(not_adverbs) {
+            let lemma = candidate_lemmas(form)
+                .into_iter()
+                .find(|candidate| dict.contains(candidate.as_str()));
+            assert_eq!(lemma, None, "{}", form);
+        }
+    }
+}
@@ -0,0 +1,274 @@
+use super::types::is_punctuation;
+use serde::{ Deserialize, Serialize };
+use std::collections::{ HashMap, HashSet };
+
+const HONORIFICS: [&str; 22] = [
+    "mr",
+    "mrs",
+    "ms",
+    "miss",
+    "master",
+    "dr",
+    "doctor",
+    "sir",
+    "dame",
+    "lady",
+    "lord",
+    "madam",
+    "madame",
+    "mme",
+    "mlle",
+    "monsieur",
+    "captain",
+    "colonel",
+    "professor",
+    "prof",
+    "uncle",
+    "aunt",
+];
+
+// a capitalised word has to be seen this many times in the middle of a sentence to be taken as a name.
+const MIN_MID_SENTENCE: usize = 2;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DetectedName {
+    pub name: String,
+    // capitalised in the middle of a sentence
+    pub capitalized: usize,
+    // written in lowercase somewhere in the book
+    pub lowercase: usize,
+    // right after an honorific such as "Mr." or "Aunt"
+    pub after_honorific: usize,
+}
+
+#[derive(Default)]
+struct WordStats {
+    name: String,
+    capitalized: usize,
+    lowercase: usize,
+    after_honorific: usize,
+}
+
+// find the character and place names in a book: capitalised words which rarely show up in lowercase,
+// or which follow an honorific. the result is sorted by how often the name is seen.
+pub fn detect_names(html: &str) -> Vec<DetectedName> {
+    let mut stats: HashMap<String, WordStats> = HashMap::new();
+    let mut sentence_start = true;
+    let mut after_honorific = false;
+
+    for token in plain_text(html).split_whitespace() {
+        if token == BLOCK_BOUNDARY {
+            sentence_start = true;
+            after_honorific = false;
+            continue;
+        }
+
+        let opens_quote = token.starts_with(['“', '"', '‘']);
+        let word = token.trim_matches(is_punctuation);
+        let word = word
+            .strip_suffix("'s")
+            .or_else(|| word.strip_suffix("’s"))
+            .unwrap_or(word);
+        let ends_sentence = token
+            .trim_end_matches(['”', '"', '’', '\'', ')'])
+            .ends_with(['.', '!', '?', ':', ';']);
+
+        if word.chars().count() > 1 && word.chars().all(|c| c.is_alphabetic() || c == '\'' || c == '-') {
+            let key = word.to_lowercase();
+            let mut chars = word.chars();
+            let first_upper = chars.next().is_some_and(char::is_uppercase);
+            // all caps is a heading or shouting, not a name
+            let title_case = first_upper && chars.any(char::is_lowercase);
+
+            if HONORIFICS.contains(&key.as_str()) {
+                after_honorific = first_upper;
+                sentence_start = ends_sentence && !token.ends_with('.');
+                continue;
+            }
+
+            let entry = stats.entry(key).or_default();
+            if title_case {
+                if entry.name.is_empty() {
+                    entry.name = word.to_string();
+                }
+                if after_honorific {
+                    entry.after_honorific += 1;
+                }
+                if !sentence_start && !opens_quote {
+                    entry.capitalized += 1;
+                }
+            } else if !first_upper {
+                entry.lowercase += 1;
+            }
+        }
+
+        after_honorific = false;
+        sentence_start = ends_sentence;
+    }
+
+    let mut names: Vec<DetectedName> = stats
+        .into_values()
+        .filter(|stats| !stats.name.is_empty())
+        .filter(|stats| {
+            stats.after_honorific > 0 ||
+                (stats.capitalized >= MIN_MID_SENTENCE && stats.lowercase * 2 <= stats.capitalized)
+        })
+        .map(|stats| DetectedName {
+            name: stats.name,
+            capitalized: stats.capitalized,
+            lowercase: stats.lowercase,
+            after_honorific: stats.after_honorific,
+        })
+        .collect();
+    names.sort_by(|a, b| {
+        (b.capitalized + b.after_honorific)
+            .cmp(&(a.capitalized + a.after_honorific))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    names
+}
+
+// the words not to annotate when capitalised: the detected names plus the ones the user added,
+// minus the ones the user said are not names.
+pub fn exclusion_list(
+    detected: &[DetectedName],
+    excluded_names: &[String],
+    included_names: &[String]
+) -> HashSet<String> {
+    let included: HashSet<String> = included_names
+        .iter()
+        .map(|name| name.to_lowercase())
+        .collect();
+
+    detected
+        .iter()
+        .map(|name| name.name.to_lowercase())
+        .chain(excluded_names.iter().map(|name| name.to_lowercase()))
+        .filter(|name| !included.contains(name))
+        .collect()
+}
+
+// whether `word` is a capitalised occurrence of an excluded name.
+pub fn is_excluded_name(word: &str, excluded: &HashSet<String>) -> bool {
+    if excluded.is_empty() {
+        return false;
+    }
+    let word = word.trim_matches(is_punctuation);
+    let word = word
+        .strip_suffix("'s")
+        .or_else(|| word.strip_suffix("’s"))
+        .unwrap_or(word);
+    word.starts_with(char::is_uppercase) && excluded.contains(&word.to_lowercase())
+}
+
+pub const BLOCK_BOUNDARY: &str = "\u{2029}";
+const BLOCK_TAGS: [&str; 14] = [
+    "p",
+    "div",
+    "br",
+    "li",
+    "td",
+    "th",
+    "tr",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "title",
+];
+
+// whether the content of a tag, what's between < and >, opens or closes a block level element.
+pub fn is_block_tag(tag: &str) -> bool {
+    let name = tag
+        .trim_start_matches('/')
+        .split(|c: char| c.is_whitespace() || c == '/')
+        .next()
+        .unwrap_or("")
+        .to_lowercase();
+    BLOCK_TAGS.contains(&name.as_str())
+}
+
+// the text of the html, block level tags become a boundary token so a heading doesn't run into the next sentence.
+pub fn plain_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut tag = String::new();
+    let mut in_tag = false;
+
+    for ch in html.chars() {
+        match ch {
+            '<' => {
+                in_tag = true;
+                tag.clear();
+            }
+            '>' if in_tag => {
+                in_tag = false;
+                if is_block_tag(&tag) {
+                    text.push(' ');
+                    text.push_str(BLOCK_BOUNDARY);
+                }
+                text.push(' ');
+            }
+            _ if in_tag => tag.push(ch),
+            _ => text.push(ch),
+        }
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_names() {
+        let html = r#"<html><head><title>Wuthering Heights</title></head><body>
+            <h1>CHAPTER I</h1>
+            <p>I have just returned from a visit to my landlord, Heathcliff. Then Heathcliff stood up.</p>
+            <p>We walked to Reading and spoke of Reading with Mr. Lockwood. “Hope is gone,” said Hope.</p>
+            <p>She was reading a book, and I felt hope as we went to see Hope again.</p>
+            <p>Mr. Earnshaw's house was cold. However, the house was grand.</p>
+        </body></html>"#;
+
+        let names = detect_names(html);
+        let found: Vec<&str> = names
+            .iter()
+            .map(|name| name.name.as_str())
+            .collect();
+        assert_eq!(found, vec!["Earnshaw", "Heathcliff", "Hope", "Lockwood", "Reading"]);
+        assert_eq!(names[4], DetectedName {
+            name: "Reading".to_string(),
+            capitalized: 2,
+            lowercase: 1,
+            after_honorific: 0,
+        });
+    }
+
+    #[test]
+    fn test_exclusion_list() {
+        let detected = vec![
+            DetectedName {
+                name: "Hope".to_string(),
+                capitalized: 3,
+                lowercase: 0,
+                after_honorific: 0,
+            },
+            DetectedName {
+                name: "Monday".to_string(),
+                capitalized: 3,
+                lowercase: 0,
+                after_honorific: 0,
+            }
+        ];
+        let excluded = exclusion_list(&detected, &["Pip".to_string()], &["monday".to_string()]);
+        assert!(excluded.contains("hope"));
+        assert!(excluded.contains("pip"));
+        assert!(!excluded.contains("monday"));
+
+        assert!(is_excluded_name("Hope,", &excluded));
+        assert!(is_excluded_name("“Pip’s", &excluded));
+        assert!(!is_excluded_name("hope", &excluded));
+    }
+}
@@ -0,0 +1,378 @@
+use super::names::is_block_tag;
+use super::progression::Fade;
+use super::types::DictRecord;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+// an annotation whose fate is decided later is written as
+// START headword SEPARATOR hint level SEPARATOR rank SEPARATOR annotated text SEPARATOR original text END
+const START: char = '\u{E000}';
+const SEPARATOR: char = '\u{E001}';
+const END: char = '\u{E002}';
+
+// the tags a new chapter starts with, compared in lowercase.
+const CHAPTER_TAGS: [&str; 3] = ["<h1", "<h2", "mbp_pagebreak"];
+
+// how many times a word is annotated, 0 means every time.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OccurrenceLimit {
+    pub max: usize,
+    pub per_chapter: bool,
+}
+
+impl OccurrenceLimit {
+    pub fn is_limited(&self) -> bool {
+        self.max > 0
+    }
+}
+
+// how many annotations a paragraph can have, or every `words` words of text when it isn't 0.
+// 0 annotations means no limit.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DensityLimit {
+    pub max: usize,
+    pub words: usize,
+}
+
+impl DensityLimit {
+    pub fn is_limited(&self) -> bool {
+        self.max > 0
+    }
+}
+
+struct Occurrence<'c> {
+    headword: &'c str,
+    hint_lvl: i32,
+    // the smaller the rarer, the rarest words keep their annotation when there are too many
+    rank: i32,
+    annotated: &'c str,
+    original: &'c str,
+    // the length of the whole marker in the chunk
+    len: usize,
+}
+
+enum Piece<'c> {
+    Tag(&'c str),
+    Text(&'c str),
+    Occurrence(Occurrence<'c>),
+}
+
+// the chunks of a book are annotated in parallel, so whether an occurrence is among the first ones, among
+// the rarest words of its paragraph, or far enough in the book to fade, can't be known at that time. the
+// annotation is written with both versions and resolve_occurrences picks one.
+pub fn push_occurrence(result: &mut String, dr: &DictRecord, rank: i32, annotated: &str, original: &str) {
+    result.push(START);
+    result.push_str(&dr.word);
+    result.push(SEPARATOR);
+    result.push_str(&dr.hint_lvl.to_string());
+    result.push(SEPARATOR);
+    result.push_str(&rank.to_string());
+    result.push(SEPARATOR);
+    result.push_str(annotated);
+    result.push(SEPARATOR);
+    result.push_str(original);
+    result.push(END);
+}
+
+// the markers can't be told apart from the same characters in the book, those are written as character
+// references before the book is annotated. they are the same characters to a reader.
+pub fn escape_markers(html: &str) -> Cow<'_, str> {
+    if !html.contains([START, SEPARATOR, END]) {
+        return Cow::Borrowed(html);
+    }
+
+    let mut escaped = String::with_capacity(html.len() + 16);
+    for ch in html.chars() {
+        match ch {
+            START | SEPARATOR | END => escaped.push_str(&format!("&#x{:X};", ch as u32)),
+            _ => escaped.push(ch),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+// keep the annotation of the rarest words where the density limit is reached, then of the words within the
+// fading hint level, then of the first `limit.max` occurrences of every headword in reading order, per book or
+// per chapter. the others get their original text back. `chunks` must be in the order they appear in the book,
+// `seen` counts the occurrences of every headword so far and can carry them over from a previous book.
+pub fn resolve_occurrences(
+    chunks: &mut [String],
+    limit: OccurrenceLimit,
+    density: DensityLimit,
+    fade: Option<Fade>,
+    seen: &mut HashMap<String, usize>
+) {
+    let kept = if density.is_limited() { thin_out(chunks, density) } else { Vec::new() };
+    let total_len = chunks.iter().map(|chunk| chunk.len()).sum::<usize>().max(1) as f64;
+    let mut offset = 0;
+    let mut index = 0;
+
+    for chunk in chunks.iter_mut() {
+        // without annotations a chunk only matters for the chapters it starts
+        if !chunk.contains(START) && !limit.per_chapter {
+            offset += chunk.len();
+            continue;
+        }
+
+        let mut resolved = String::with_capacity(chunk.len());
+        walk(chunk, |piece| {
+            match piece {
+                Piece::Tag(tag) => {
+                    if limit.per_chapter && has_chapter_tag(tag) {
+                        seen.clear();
+                    }
+                    resolved.push_str(tag);
+                    offset += tag.len();
+                }
+                Piece::Text(text) => {
+                    resolved.push_str(text);
+                    offset += text.len();
+                }
+                Piece::Occurrence(occurrence) => {
+                    let position = (offset as f64) / total_len;
+                    let max = fade.map_or(limit.max, |fade| fade.occurrences_at(position));
+                    let mut keep = kept.get(index).copied().unwrap_or(true);
+                    index += 1;
+                    if let Some(hint_level) = fade.and_then(|fade| fade.hint_level_at(position)) {
+                        keep = keep && occurrence.hint_lvl <= hint_level;
+                    }
+                    if keep && max > 0 {
+                        let count = seen.entry(occurrence.headword.to_string()).or_insert(0);
+                        *count += 1;
+                        keep = *count <= max;
+                    }
+                    resolved.push_str(if keep { occurrence.annotated } else { occurrence.original });
+                    offset += occurrence.len;
+                }
+            }
+        });
+
+        *chunk = resolved;
+    }
+}
+
+// which occurrences, in reading order, are within the density limit.
+fn thin_out(chunks: &[String], density: DensityLimit) -> Vec<bool> {
+    let mut kept = Vec::new();
+    let mut group: Vec<(usize, i32)> = Vec::new();
+    let mut words = 0;
+
+    for chunk in chunks {
+        walk(chunk, |piece| {
+            match piece {
+                Piece::Tag(tag) => {
+                    let tag = tag.trim_start_matches('<').trim_end_matches('>');
+                    if density.words == 0 && is_block_tag(tag) {
+                        keep_rarest(&mut group, &mut kept, density.max);
+                    }
+                }
+                Piece::Text(text) => {
+                    words += text.split_whitespace().count();
+                }
+                Piece::Occurrence(occurrence) => {
+                    group.push((kept.len(), occurrence.rank));
+                    kept.push(true);
+                    words += occurrence.original.split_whitespace().count();
+                }
+            }
+            if density.words > 0 && words >= density.words {
+                keep_rarest(&mut group, &mut kept, density.max);
+                words = 0;
+            }
+        });
+    }
+    keep_rarest(&mut group, &mut kept, density.max);
+
+    kept
+}
+
+fn keep_rarest(group: &mut Vec<(usize, i32)>, kept: &mut [bool], max: usize) {
+    if group.len() > max {
+        group.sort_by_key(|&(index, rank)| (rank, index));
+        for &(index, _) in &group[max..] {
+            kept[index] = false;
+        }
+    }
+    group.clear();
+}
+
+// cut html into tags, text and occurrences.
+fn walk<'c>(html: &'c str, mut visit: impl FnMut(Piece<'c>)) {
+    let mut rest = html;
+
+    while let Some(at) = rest.find(['<', START]) {
+        if at > 0 {
+            visit(Piece::Text(&rest[..at]));
+        }
+        rest = &rest[at..];
+
+        if rest.starts_with(START) {
+            let Some(end) = rest.find(END) else {
+                break;
+            };
+            let mut parts = rest[START.len_utf8()..end].splitn(5, SEPARATOR);
+            visit(
+                Piece::Occurrence(Occurrence {
+                    headword: parts.next().unwrap_or(""),
+                    hint_lvl: parts
+                        .next()
+                        .and_then(|hint_lvl| hint_lvl.parse().ok())
+                        .unwrap_or_default(),
+                    rank: parts
+                        .next()
+                        .and_then(|rank| rank.parse().ok())
+                        .unwrap_or_default(),
+                    annotated: parts.next().unwrap_or(""),
+                    original: parts.next().unwrap_or(""),
+                    len: end + END.len_utf8(),
+                })
+            );
+            rest = &rest[end + END.len_utf8()..];
+        } else {
+            let end = rest.find('>').map_or(rest.len(), |end| end + 1);
+            visit(Piece::Tag(&rest[..end]));
+            rest = &rest[end..];
+        }
+    }
+
+    if !rest.is_empty() {
+        visit(Piece::Text(rest));
+    }
+}
+
+fn has_chapter_tag(html: &str) -> bool {
+    let html = html.to_lowercase();
+    CHAPTER_TAGS.iter().any(|tag| html.contains(tag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::progression::Progression;
+    use super::*;
+
+    // the test words are ranked by their hint level
+    fn marked(headword: &str, hint_lvl: i32, original: &str) -> String {
+        let dr = DictRecord { word: headword.to_string(), hint_lvl, ..Default::default() };
+        let mut result = String::new();
+        push_occurrence(&mut result, &dr, hint_lvl, &format!("[{}]", original), original);
+        result
+    }
+
+    #[test]
+    fn test_resolve_occurrences() {
+        let chunks = vec![
+            format!("<p>{} and {}</p>", marked("sociable", 3, "sociable"), marked("utter", 2, "uttering")),
+            format!("<p>{} again</p><h2>Chapter 2</h2>", marked("sociable", 3, "Sociable")),
+            format!("<p>{} and {}</p>", marked("utter", 2, "utter"), marked("sociable", 3, "sociable"))
+        ];
+        let no_density = DensityLimit::default();
+
+        let mut per_book = chunks.clone();
+        resolve_occurrences(&mut per_book, OccurrenceLimit { max: 1, per_chapter: false }, no_density, None, &mut HashMap::new());
+        assert_eq!(per_book, vec![
+            "<p>[sociable] and [uttering]</p>",
+            "<p>Sociable again</p><h2>Chapter 2</h2>",
+            "<p>utter and sociable</p>",
+        ]);
+
+        let mut per_chapter = chunks.clone();
+        resolve_occurrences(&mut per_chapter, OccurrenceLimit { max: 1, per_chapter: true }, no_density, None, &mut HashMap::new());
+        assert_eq!(per_chapter, vec![
+            "<p>[sociable] and [uttering]</p>",
+            "<p>Sociable again</p><h2>Chapter 2</h2>",
+            "<p>[utter] and [sociable]</p>",
+        ]);
+
+        let mut twice = chunks;
+        resolve_occurrences(&mut twice, OccurrenceLimit { max: 2, per_chapter: false }, no_density, None, &mut HashMap::new());
+        assert_eq!(twice, vec![
+            "<p>[sociable] and [uttering]</p>",
+            "<p>[Sociable] again</p><h2>Chapter 2</h2>",
+            "<p>[utter] and sociable</p>",
+        ]);
+    }
+
+    #[test]
+    fn test_escape_markers() {
+        // the book is escaped before it's annotated
+        let book = escape_markers("<p title=\"\u{E001}\">\u{E000} sociable\u{E002}</p>");
+        let mut chunks = vec![book.replace("sociable", &marked("sociable", 3, "sociable"))];
+        resolve_occurrences(
+            &mut chunks,
+            OccurrenceLimit { max: 1, per_chapter: false },
+            DensityLimit::default(),
+            None,
+            &mut HashMap::new()
+        );
+        assert_eq!(chunks, vec!["<p title=\"&#xE001;\">&#xE000; [sociable]&#xE002;</p>"]);
+        assert!(matches!(escape_markers("<p>sociable</p>"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_density_limit() {
+        let html = format!(
+            "<p>{} {} and {}</p><p>an {} fool</p>",
+            marked("sociable", 3, "sociable"),
+            marked("versatile", 1, "versatile"),
+            marked("ribose", 1, "ribose"),
+            marked("utter", 2, "utter")
+        );
+        let no_limit = OccurrenceLimit::default();
+
+        let mut per_paragraph = vec![html.clone()];
+        resolve_occurrences(&mut per_paragraph, no_limit, DensityLimit { max: 1, words: 0 }, None, &mut HashMap::new());
+        assert_eq!(per_paragraph, vec!["<p>sociable [versatile] and ribose</p><p>an [utter] fool</p>"]);
+
+        // the rarest word of every 3 words, whatever the paragraphs
+        let mut per_words = vec![html.clone()];
+        resolve_occurrences(&mut per_words, no_limit, DensityLimit { max: 1, words: 3 }, None, &mut HashMap::new());
+        assert_eq!(per_words, vec!["<p>sociable [versatile] and [ribose]</p><p>an utter fool</p>"]);
+
+        // the words dropped for density don't use up their first occurrence
+        let mut both = vec![html, format!("<p>{}</p>", marked("ribose", 1, "ribose"))];
+        resolve_occurrences(
+            &mut both,
+            OccurrenceLimit { max: 1, per_chapter: false },
+            DensityLimit { max: 1, words: 0 },
+            None,
+            &mut HashMap::new()
+        );
+        assert_eq!(both, vec![
+            "<p>sociable [versatile] and ribose</p><p>an [utter] fool</p>",
+            "<p>[ribose]</p>",
+        ]);
+    }
+
+    #[test]
+    fn test_fade() {
+        let progression = Progression { end_hint_level: 1, end_occurrences: 1, ..Default::default() };
+        let fade = Fade::new(&progression, Some(3), 2, 0);
+        let mut faded = vec![
+            format!(
+                "<p>{} {} {}</p>",
+                marked("sociable", 3, "sociable"),
+                marked("ribose", 1, "ribose"),
+                marked("ribose", 1, "ribose")
+            ),
+            format!(
+                "<p>{} {} {}</p>",
+                marked("sociable", 3, "sociable"),
+                marked("utter", 2, "utter"),
+                marked("ribose", 1, "ribose")
+            )
+        ];
+
+        // the hint level goes from 3 to 1 and the occurrences from 2 to 1, ribose was seen in a previous book.
+        // sociable is dropped in the second half, utter is still wanted there.
+        let mut seen = HashMap::from([("ribose".to_string(), 1)]);
+        resolve_occurrences(
+            &mut faded,
+            OccurrenceLimit { max: 2, per_chapter: false },
+            DensityLimit::default(),
+            Some(fade),
+            &mut seen
+        );
+        assert_eq!(faded, vec!["<p>[sociable] [ribose] ribose</p>", "<p>sociable [utter] ribose</p>"]);
+        assert_eq!(seen.get("ribose"), Some(&4));
+    }
+}
@@ -0,0 +1,210 @@
+use super::types::Payload;
+use serde::{ Deserialize, Serialize };
+use std::fs::{ self, OpenOptions };
+use std::path::{ Path, PathBuf };
+use uuid::Uuid;
+
+// the annotated book is named like this when nothing else is set.
+pub const DEFAULT_OUTPUT_NAME: &str = "{stem}-wordwise.{ext}";
+// {stem} the book's name without its extension, {ext} the output format, {lang} the wordwise language,
+// {book_lang} the book's language and {level} the hint level.
+const NAME_FIELDS: [&str; 5] = ["stem", "ext", "lang", "book_lang", "level"];
+
+// what to do when the annotated book is already there.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OverwritePolicy {
+    #[default]
+    Overwrite,
+    // leave the file there and don't annotate the book
+    Skip,
+    // "Dune-wordwise (2).epub", the first number free
+    Suffix,
+}
+
+// how a job ended when it didn't fail.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Artifact {
+    Saved(PathBuf),
+    // the annotated book was there already and the policy is to skip it
+    Skipped(PathBuf),
+}
+
+impl Artifact {
+    pub fn path(&self) -> &Path {
+        match self {
+            Artifact::Saved(path) | Artifact::Skipped(path) => path,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        let path = self.path();
+        let name = path.file_name().unwrap().to_string_lossy();
+        let folder = path.parent().unwrap().to_string_lossy();
+        match self {
+            Artifact::Saved(_) => format!("{} save to {}", name, folder),
+            Artifact::Skipped(_) => format!("{} is already in {}, skipped", name, folder),
+        }
+    }
+}
+
+// the fields of the template, an error names what can't be used.
+pub fn check_output_name(template: &str) -> Result<(), String> {
+    if template.trim().is_empty() {
+        return Err("the output name is empty".to_string());
+    }
+    if template.contains(['/', '\\']) {
+        return Err(format!("the output name \"{}\" can't have a folder in it", template));
+    }
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .ok_or(format!("the output name \"{}\" has a \"{{\" without \"}}\"", template))?;
+        let field = &rest[start + 1..start + end];
+        if !NAME_FIELDS.contains(&field) {
+            return Err(format!("the output name \"{}\" has an unknown field {{{}}}", template, field));
+        }
+        rest = &rest[start + end + 1..];
+    }
+    Ok(())
+}
+
+// the name of the annotated book, the template filled in for the payload's book.
+pub fn output_name(payload: &Payload) -> Result<String, String> {
+    let template = if payload.output_name.is_empty() { DEFAULT_OUTPUT_NAME } else { payload.output_name.as_str() };
+    check_output_name(template)?;
+    let stem = Path::new(&payload.book)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    Ok(
+        template
+            .replace("{stem}", &stem)
+            .replace("{ext}", &payload.format)
+            .replace("{book_lang}", &payload.book_language)
+            .replace("{lang}", &payload.language)
+            .replace("{level}", &payload.hint_level.to_string())
+    )
+}
+
+// the payload's folder, else the one of the settings, else the book's own folder.
+pub fn output_folder(payload: &Payload, default_dir: &str) -> PathBuf {
+    if !payload.output_dir.is_empty() {
+        PathBuf::from(&payload.output_dir)
+    } else if !default_dir.is_empty() {
+        PathBuf::from(default_dir)
+    } else {
+        Path::new(&payload.book).parent().map(Path::to_path_buf).unwrap_or_default()
+    }
+}
+
+// where the book goes in `folder` under the policy.
+pub fn destination(folder: &Path, name: &str, policy: OverwritePolicy) -> Artifact {
+    let path = folder.join(name);
+    if !path.exists() {
+        return Artifact::Saved(path);
+    }
+    match policy {
+        OverwritePolicy::Overwrite => Artifact::Saved(path),
+        OverwritePolicy::Skip => Artifact::Skipped(path),
+        OverwritePolicy::Suffix => {
+            let name = Path::new(name);
+            let stem = name.file_stem().unwrap_or_default().to_string_lossy();
+            let extension = name
+                .extension()
+                .map(|extension| format!(".{}", extension.to_string_lossy()))
+                .unwrap_or_default();
+            let path = (2..)
+                .map(|count| folder.join(format!("{} ({}){}", stem, count, extension)))
+                .find(|path| !path.exists())
+                .unwrap();
+            Artifact::Saved(path)
+        }
+    }
+}
+
+// an output name and folder which give the book's own path would overwrite it with the annotated one.
+pub fn check_not_source(book: &Path, artifact: &Path) -> Result<(), String> {
+    let same = match (fs::canonicalize(book), fs::canonicalize(artifact)) {
+        (Ok(book), Ok(artifact)) => book == artifact,
+        _ => book == artifact,
+    };
+    if same {
+        return Err(
+            format!(
+                "{} would replace the book itself, please change the output name or folder.",
+                artifact.display()
+            )
+        );
+    }
+    Ok(())
+}
+
+// the folder is created if needed and a file is written and removed in it, so a folder that can't be
+// written fails before the conversion and not after it.
+pub fn check_writable(folder: &Path) -> Result<(), String> {
+    let cannot_write = |e: std::io::Error| format!("Can't write to {}: {}", folder.display(), e);
+    fs::create_dir_all(folder).map_err(cannot_write)?;
+    let probe = folder.join(format!(".ebook-wordwise-{}.tmp", Uuid::new_v4()));
+    OpenOptions::new().write(true).create_new(true).open(&probe).map_err(cannot_write)?;
+    fs::remove_file(&probe).map_err(cannot_write)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::store::TestDir;
+    use super::*;
+
+    #[test]
+    fn test_output_name() {
+        let payload = Payload { book: "/books/Dune.mobi".to_string(), hint_level: 4, ..Default::default() };
+        assert_eq!(output_name(&payload).unwrap(), "Dune-wordwise.epub");
+
+        let payload = Payload { output_name: "{stem}-{lang}-L{level}.{ext}".to_string(), ..payload };
+        assert_eq!(output_name(&payload).unwrap(), "Dune-en-L4.epub");
+
+        assert!(check_output_name("{book_lang}/{stem}.{ext}").is_err());
+        assert!(check_output_name("{title}.{ext}").is_err());
+        assert!(check_output_name("{stem.{ext}").is_err());
+        assert!(check_output_name(" ").is_err());
+    }
+
+    #[test]
+    fn test_destination() {
+        let folder = TestDir::new("output");
+        check_writable(&folder).unwrap();
+        assert_eq!(fs::read_dir(&folder).unwrap().count(), 0);
+
+        let name = "Dune-wordwise.epub";
+        let path = folder.join(name);
+        assert_eq!(destination(&folder, name, OverwritePolicy::Skip), Artifact::Saved(path.clone()));
+
+        fs::write(&path, "").unwrap();
+        fs::write(folder.join("Dune-wordwise (2).epub"), "").unwrap();
+        assert_eq!(destination(&folder, name, OverwritePolicy::Overwrite), Artifact::Saved(path.clone()));
+        assert_eq!(destination(&folder, name, OverwritePolicy::Skip), Artifact::Skipped(path));
+        assert_eq!(
+            destination(&folder, name, OverwritePolicy::Suffix),
+            Artifact::Saved(folder.join("Dune-wordwise (3).epub"))
+        );
+
+        // "{stem}.{ext}" in the book's folder is the book itself
+        let book = folder.join("Dune.epub");
+        fs::write(&book, "").unwrap();
+        let payload = Payload {
+            book: book.to_string_lossy().into_owned(),
+            output_name: "{stem}.{ext}".to_string(),
+            ..Default::default()
+        };
+        let folder_of_book = output_folder(&payload, "");
+        let artifact = destination(&folder_of_book, &output_name(&payload).unwrap(), OverwritePolicy::Overwrite);
+        assert!(check_not_source(&book, artifact.path()).is_err());
+        assert!(check_not_source(&book, &folder.join(".").join("Dune.epub")).is_err());
+        assert!(check_not_source(&book, &folder.join(name)).is_ok());
+        assert_eq!(
+            destination(&folder, "Dune.epub", OverwritePolicy::Suffix),
+            Artifact::Saved(folder.join("Dune (2).epub"))
+        );
+    }
+}
@@ -0,0 +1,256 @@
+use super::types::{ is_punctuation, DictRecord };
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+pub const DEFAULT_MAX_PHRASE_LENGTH: usize = 5;
+
+#[derive(Default)]
+struct Node {
+    children: HashMap<String, usize>,
+    headword: Option<String>,
+}
+
+// a token trie over the dictionary headwords, e.g. "in someone's pocket" is stored as
+// root -> "in" -> "someone's" -> "pocket". it's built once per loaded dictionary and matching
+// only walks borrowed slices of the sentence, nothing is allocated per token.
+pub struct PhraseMatcher {
+    nodes: Vec<Node>,
+    max_phrase_length: usize,
+}
+
+impl PhraseMatcher {
+    pub fn new(dict: &HashMap<String, DictRecord>, max_phrase_length: usize) -> Self {
+        let mut matcher = Self {
+            nodes: vec![Node::default()],
+            max_phrase_length: max_phrase_length.max(1),
+        };
+        for headword in dict.keys() {
+            matcher.insert(headword);
+        }
+        matcher
+    }
+
+    fn insert(&mut self, headword: &str) {
+        let mut current = 0;
+        let mut length = 0;
+        for token in headword.split_whitespace() {
+            length += 1;
+            current = match self.nodes[current].children.get(token) {
+                Some(&next) => next,
+                None => {
+                    let next = self.nodes.len();
+                    self.nodes.push(Node::default());
+                    self.nodes[current].children.insert(token.to_string(), next);
+                    next
+                }
+            };
+        }
+
+        if length > 0 && length <= self.max_phrase_length {
+            self.nodes[current].headword = Some(headword.to_string());
+        }
+    }
+
+    // find the longest headword starting at words[start], returns the headword and how many words it covers.
+    // punctuation is trimmed from the outer edges of the phrase only, so "pocket," can end a phrase
+    // but "pocket, but" can't match "pocket but".
+    // every word is also tried by its lemma and pronouns can stand in for the dictionary's placeholders,
+    // so "kicked the bucket" finds "kick the bucket" and "in his pocket" finds "in someone's pocket".
+    pub fn longest_match<'m>(
+        &'m self,
+        tokens: &Tokens,
+        start: usize,
+        lemma_dict: &HashMap<String, String>
+    ) -> Option<(&'m str, usize)> {
+        let end = tokens.len().min(start + self.max_phrase_length);
+        let mut states = [0; MAX_ACTIVE_STATES];
+        let mut active = 1;
+        let mut longest = None;
+
+        for k in start..end {
+            // lowercasing leaves the punctuation alone, the same trimming fits both
+            let (token, last) = trim_token(tokens.words[k], k == start);
+            let (lowercase_token, lowercase_last) = trim_token(&tokens.lowercase[k], k == start);
+
+            for &state in &states[..active] {
+                // a lone pronoun is never a match for a placeholder headword like "someone's"
+                let found = self
+                    .next_nodes(state, last, lowercase_last, lemma_dict, k > start)
+                    .find_map(|node| self.nodes[node].headword.as_deref());
+                if let Some(headword) = found {
+                    longest = Some((headword, k - start + 1));
+                    break;
+                }
+            }
+
+            let mut next_states = [0; MAX_ACTIVE_STATES];
+            let mut next_active = 0;
+            for &state in &states[..active] {
+                for node in self.next_nodes(state, token, lowercase_token, lemma_dict, true) {
+                    if next_active < MAX_ACTIVE_STATES && !next_states[..next_active].contains(&node) {
+                        next_states[next_active] = node;
+                        next_active += 1;
+                    }
+                }
+            }
+
+            if next_active == 0 {
+                break;
+            }
+            states = next_states;
+            active = next_active;
+        }
+
+        longest
+    }
+
+    // the children of `state` reachable by `token`: as written, lowercased, by its lemma and by placeholder.
+    fn next_nodes<'s>(
+        &'s self,
+        state: usize,
+        token: &'s str,
+        lowercase: &'s str,
+        lemma_dict: &'s HashMap<String, String>,
+        placeholders: bool
+    ) -> impl Iterator<Item = usize> + 's {
+        let children = &self.nodes[state].children;
+        let lemma = lemma_dict.get(lowercase).map(|lemma| lemma.as_str());
+        let substitutes = if placeholders { placeholders_for(lowercase) } else { &[] };
+
+        let exact = children.get(token).copied();
+        let lowered = if lowercase != token { children.get(lowercase).copied() } else { None };
+        let lemmatized = lemma.and_then(|lemma| children.get(lemma).copied());
+
+        exact
+            .into_iter()
+            .chain(lowered)
+            .chain(lemmatized)
+            .chain(substitutes.iter().filter_map(move |placeholder| children.get(*placeholder).copied()))
+    }
+}
+
+const MAX_ACTIVE_STATES: usize = 8;
+
+// the word as it goes on along a phrase, and as it ends one. only the first word of a phrase loses its
+// leading punctuation.
+fn trim_token(word: &str, first: bool) -> (&str, &str) {
+    let token = if first { word.trim_start_matches(is_punctuation) } else { word };
+    (token, token.trim_end_matches(is_punctuation))
+}
+
+// the words of a sentence and their lowercase, made once for every match starting in the sentence.
+// a word without a capital is only borrowed.
+pub struct Tokens<'t> {
+    words: &'t [&'t str],
+    lowercase: Vec<Cow<'t, str>>,
+}
+
+impl<'t> Tokens<'t> {
+    pub fn new(words: &'t [&'t str]) -> Self {
+        let lowercase = words
+            .iter()
+            .map(|word| {
+                if word.chars().any(char::is_uppercase) {
+                    Cow::Owned(word.to_lowercase())
+                } else {
+                    Cow::Borrowed(*word)
+                }
+            })
+            .collect();
+        Self { words, lowercase }
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+}
+
+// the placeholders idioms are written with in the dictionary, and the words that can take their place in prose.
+fn placeholders_for(word: &str) -> &'static [&'static str] {
+    match word {
+        "her" => &["someone's", "one's", "someone"],
+        "my" | "your" | "his" | "its" | "our" | "their" => &["someone's", "one's"],
+        "me" | "you" | "him" | "us" | "them" => &["someone"],
+        "myself" | "yourself" | "himself" | "herself" | "itself" | "ourselves" | "themselves" => {
+            &["oneself"]
+        }
+        "it's" | "he's" | "she's" | "that's" | "there's" | "here's" | "what's" | "who's" | "let's" => {
+            &[]
+        }
+        // a possessive name, "in John's pocket"
+        _ if word.ends_with("'s") || word.ends_with("’s") => &["someone's"],
+        _ => &[],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(word: &str) -> DictRecord {
+        DictRecord {
+            word: word.to_string(),
+            phoneme: "".to_string(),
+            full_def: "".to_string(),
+            short_def: "".to_string(),
+            example_sentences: "".to_string(),
+            hint_lvl: 1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_longest_match() {
+        let mut dict = HashMap::new();
+        for word in ["in", "in someone's pocket", "pocket", "a long way to go"] {
+            dict.insert(word.to_string(), record(word));
+        }
+        let matcher = PhraseMatcher::new(&dict, DEFAULT_MAX_PHRASE_LENGTH);
+
+        let lemma = HashMap::new();
+        let words: Vec<&str> = "this is in someone's pocket, but".split_whitespace().collect();
+        assert_eq!(matcher.longest_match(&Tokens::new(&words), 2, &lemma), Some(("in someone's pocket", 3)));
+        assert_eq!(matcher.longest_match(&Tokens::new(&words), 4, &lemma), Some(("pocket", 1)));
+        assert_eq!(matcher.longest_match(&Tokens::new(&words), 0, &lemma), None);
+
+        let words: Vec<&str> = "in someone's pocket, but".split_whitespace().collect();
+        assert_eq!(matcher.longest_match(&Tokens::new(&words[..2]), 0, &lemma), Some(("in", 1)));
+
+        let words: Vec<&str> = "(a long way to go)".split_whitespace().collect();
+        assert_eq!(matcher.longest_match(&Tokens::new(&words), 0, &lemma), Some(("a long way to go", 5)));
+        let short = PhraseMatcher::new(&dict, 3);
+        assert_eq!(short.longest_match(&Tokens::new(&words), 0, &lemma), None);
+    }
+
+    #[test]
+    fn test_inflected_match() {
+        let mut dict = HashMap::new();
+        for word in ["kick the bucket", "give up", "in someone's pocket", "lose one's temper", "pull oneself together"] {
+            dict.insert(word.to_string(), record(word));
+        }
+        let mut lemma = HashMap::new();
+        for (form, normal_form) in [("kicked", "kick"), ("gave", "give"), ("loses", "lose"), ("lost", "lose")] {
+            lemma.insert(form.to_string(), normal_form.to_string());
+        }
+        let matcher = PhraseMatcher::new(&dict, DEFAULT_MAX_PHRASE_LENGTH);
+
+        let data = [
+            ("He kicked the bucket.", 1, Some(("kick the bucket", 3))),
+            ("Gave up", 0, Some(("give up", 2))),
+            ("this is in his pocket", 2, Some(("in someone's pocket", 3))),
+            ("this is in John's pocket", 2, Some(("in someone's pocket", 3))),
+            ("this is in it's pocket", 2, None),
+            ("she lost her temper", 1, Some(("lose one's temper", 3))),
+            ("pull yourself together", 0, Some(("pull oneself together", 3))),
+            ("he kicked a bucket", 1, None),
+        ];
+        for (sentence, start, expected) in data {
+            let words: Vec<&str> = sentence.split_whitespace().collect();
+            assert_eq!(matcher.longest_match(&Tokens::new(&words), start, &lemma), expected, "{}", sentence);
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use super::types::DictRecord;
+use serde::{ Deserialize, Serialize };
+use std::collections::{ BTreeMap, HashMap, HashSet };
+
+// how many words of every hint level the test asks about.
+const WORDS_PER_LEVEL: usize = 10;
+// a reader who knows this share of a level's words doesn't need them annotated.
+const KNOWN_SHARE: f64 = 0.8;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PlacementQuestion {
+    pub word: String,
+    pub hint_lvl: i32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PlacementAnswer {
+    pub word: String,
+    pub known: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LevelScore {
+    pub hint_lvl: i32,
+    // how many headwords of this level the dictionary has
+    pub headwords: usize,
+    pub asked: usize,
+    pub known: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PlacementResult {
+    // the dictionary headwords the reader is estimated to know
+    pub vocabulary_size: usize,
+    pub hint_level: i32,
+    pub levels: Vec<LevelScore>,
+    // the words the reader said they know, to seed a known words list
+    pub known_words: Vec<String>,
+}
+
+// a yes/no vocabulary test made of headwords sampled from every hint level of the dictionary.
+pub struct PlacementTest {
+    pub questions: Vec<PlacementQuestion>,
+    level_sizes: BTreeMap<i32, usize>,
+}
+
+impl PlacementTest {
+    // `seed` picks the words, the same seed asks the same words.
+    pub fn new(dict: &HashMap<String, DictRecord>, seed: u64) -> Self {
+        let mut levels: BTreeMap<i32, Vec<&str>> = BTreeMap::new();
+        for dr in dict.values() {
+            // phrases and abbreviations don't say much about a reader's vocabulary
+            if dr.word.chars().all(char::is_alphabetic) {
+                levels.entry(dr.hint_lvl).or_default().push(dr.word.as_str());
+            }
+        }
+
+        let mut rng = seed.max(1);
+        let mut questions = Vec::new();
+        let mut level_sizes = BTreeMap::new();
+        for (hint_lvl, mut words) in levels {
+            level_sizes.insert(hint_lvl, words.len());
+            // the dictionary's order changes from run to run, the seed alone decides
+            words.sort_unstable();
+            for i in 0..WORDS_PER_LEVEL.min(words.len()) {
+                let j = i + ((next_random(&mut rng) % ((words.len() - i) as u64)) as usize);
+                words.swap(i, j);
+                questions.push(PlacementQuestion { word: words[i].to_string(), hint_lvl });
+            }
+        }
+
+        // mix the levels so the hard words don't all come at the end
+        for i in (1..questions.len()).rev() {
+            let j = (next_random(&mut rng) % ((i + 1) as u64)) as usize;
+            questions.swap(i, j);
+        }
+
+        PlacementTest { questions, level_sizes }
+    }
+
+    // estimate the reader's vocabulary from the answers and recommend the hint level which annotates the
+    // levels they know less than 80% of. unanswered questions count as unknown words.
+    pub fn score(&self, answers: &[PlacementAnswer]) -> PlacementResult {
+        let known: HashSet<&str> = answers
+            .iter()
+            .filter(|answer| answer.known)
+            .map(|answer| answer.word.as_str())
+            .collect();
+
+        let mut levels: Vec<LevelScore> = self.level_sizes
+            .iter()
+            .map(|(&hint_lvl, &headwords)| LevelScore { hint_lvl, headwords, asked: 0, known: 0 })
+            .collect();
+        let mut known_words = Vec::new();
+        for question in &self.questions {
+            if let Some(level) = levels.iter_mut().find(|level| level.hint_lvl == question.hint_lvl) {
+                level.asked += 1;
+                if known.contains(question.word.as_str()) {
+                    level.known += 1;
+                    known_words.push(question.word.clone());
+                }
+            }
+        }
+
+        let vocabulary_size = levels
+            .iter()
+            .filter(|level| level.asked > 0)
+            .map(|level| ((level.headwords * level.known) as f64) / (level.asked as f64))
+            .sum::<f64>()
+            .round() as usize;
+
+        // hint level 1 words are the rarest, the hint level annotates its level and the rarer ones
+        let hint_level = levels
+            .iter()
+            .filter(|level| level.asked > 0)
+            .filter(|level| (level.known as f64) < (level.asked as f64) * KNOWN_SHARE)
+            .map(|level| level.hint_lvl)
+            .max()
+            .or_else(|| levels.first().map(|level| level.hint_lvl))
+            .unwrap_or(1);
+
+        known_words.sort();
+        PlacementResult { vocabulary_size, hint_level, levels, known_words }
+    }
+}
+
+// xorshift, the test only needs words that look picked at random.
+fn next_random(state: &mut u64) -> u64 {
+    let mut x = *state;
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    *state = x;
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // two letters for a number, the headwords have to be alphabetic
+    fn letters(n: usize) -> String {
+        format!("{}{}", (b'a' + ((n / 26) as u8)) as char, (b'a' + ((n % 26) as u8)) as char)
+    }
+
+    fn dict() -> HashMap<String, DictRecord> {
+        let mut dict = HashMap::new();
+        for hint_lvl in 1..=5 {
+            for i in 0..40 {
+                let word = format!("{}{}", letters(hint_lvl as usize), letters(i));
+                dict.insert(word.clone(), DictRecord { word, hint_lvl, ..Default::default() });
+            }
+        }
+        dict.insert("give up".to_string(), DictRecord {
+            word: "give up".to_string(),
+            hint_lvl: 1,
+            ..Default::default()
+        });
+        dict
+    }
+
+    #[test]
+    fn test_placement_test() {
+        let dict = dict();
+        let test = PlacementTest::new(&dict, 42);
+        assert_eq!(test.questions.len(), 50);
+        assert!(!test.questions.iter().any(|question| question.word.contains(' ')));
+        let asked: HashSet<&str> = test.questions
+            .iter()
+            .map(|question| question.word.as_str())
+            .collect();
+        assert_eq!(asked.len(), 50);
+        assert_eq!(PlacementTest::new(&dict, 42).questions, test.questions);
+
+        // the reader knows the common words of levels 4 and 5, and half of level 3
+        let mut level_3 = 0;
+        let answers: Vec<PlacementAnswer> = test.questions
+            .iter()
+            .map(|question| {
+                let known = match question.hint_lvl {
+                    4 | 5 => true,
+                    3 => {
+                        level_3 += 1;
+                        level_3 % 2 == 0
+                    }
+                    _ => false,
+                };
+                PlacementAnswer { word: question.word.clone(), known }
+            })
+            .collect();
+
+        let result = test.score(&answers);
+        assert_eq!(result.hint_level, 3);
+        assert_eq!(result.vocabulary_size, 40 + 40 + 20);
+        assert_eq!(result.known_words.len(), 25);
+        assert_eq!(result.levels[0], LevelScore { hint_lvl: 1, headwords: 40, asked: 10, known: 0 });
+
+        // everything known, only the rarest level is left to annotate
+        let answers: Vec<PlacementAnswer> = test.questions
+            .iter()
+            .map(|question| PlacementAnswer { word: question.word.clone(), known: true })
+            .collect();
+        assert_eq!(test.score(&answers).hint_level, 1);
+        assert_eq!(test.score(&[]).hint_level, 5);
+    }
+}
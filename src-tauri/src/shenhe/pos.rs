@@ -0,0 +1,155 @@
+use super::types::{ is_punctuation, DictRecord, PartOfSpeech };
+use std::borrow::Cow;
+
+const DETERMINERS: [&str; 17] = [
+    "a",
+    "an",
+    "the",
+    "this",
+    "that",
+    "these",
+    "those",
+    "my",
+    "your",
+    "his",
+    "her",
+    "its",
+    "our",
+    "their",
+    "some",
+    "every",
+    "no",
+];
+const VERB_CUES: [&str; 20] = [
+    "to",
+    "will",
+    "would",
+    "can",
+    "could",
+    "shall",
+    "should",
+    "may",
+    "might",
+    "must",
+    "do",
+    "does",
+    "did",
+    "i",
+    "you",
+    "he",
+    "she",
+    "we",
+    "they",
+    "not",
+];
+const INTENSIFIERS: [&str; 8] = ["very", "too", "so", "quite", "rather", "more", "most", "less"];
+const LINKING_VERBS: [&str; 8] = [
+    "is",
+    "was",
+    "are",
+    "were",
+    "be",
+    "been",
+    "seems",
+    "looks",
+];
+
+// pick the sense of `dr` that fits how words[i] is used in the sentence, a record with one sense is returned as is.
+pub fn select_sense<'a>(dr: &'a DictRecord, words: &[&str], i: usize) -> Cow<'a, DictRecord> {
+    if dr.senses.is_empty() {
+        return Cow::Borrowed(dr);
+    }
+    dr.for_pos(tag(words, i, dr.word.as_str()))
+}
+
+// a lightweight guess at the part of speech of words[i], which was found in the dictionary as `headword`.
+// the inflection says the most ("uttering" came from a verb), then the word right before it.
+pub fn tag(words: &[&str], i: usize, headword: &str) -> PartOfSpeech {
+    let word = words[i].trim_matches(is_punctuation).to_lowercase();
+    let prev = if i > 0 { words[i - 1].trim_matches(is_punctuation).to_lowercase() } else { String::new() };
+    let prev = prev.as_str();
+
+    if word != headword {
+        if word.ends_with("ing") || word.ends_with("ed") {
+            // "very tired" is an adjective, "was tired" is left to the verb
+            if INTENSIFIERS.contains(&prev) {
+                return PartOfSpeech::Adjective;
+            }
+            return PartOfSpeech::Verb;
+        }
+        if word.ends_with("ly") && !headword.ends_with("ly") {
+            return PartOfSpeech::Adverb;
+        }
+        if (word.ends_with("er") || word.ends_with("est")) && !headword.ends_with("er") {
+            return PartOfSpeech::Adjective;
+        }
+        if word.ends_with('s') {
+            return match prev {
+                "he" | "she" | "it" | "who" | "which" | "that" => PartOfSpeech::Verb,
+                _ => PartOfSpeech::Noun,
+            };
+        }
+    }
+
+    if DETERMINERS.contains(&prev) {
+        PartOfSpeech::Noun
+    } else if VERB_CUES.contains(&prev) {
+        PartOfSpeech::Verb
+    } else if INTENSIFIERS.contains(&prev) || LINKING_VERBS.contains(&prev) {
+        PartOfSpeech::Adjective
+    } else {
+        PartOfSpeech::Unknown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::types::Sense;
+    use super::*;
+
+    #[test]
+    fn test_select_sense() {
+        let dr = DictRecord {
+            word: "utter".to_string(),
+            full_def: "complete and total".to_string(),
+            short_def: "complete and total".to_string(),
+            pos: PartOfSpeech::Adjective,
+            senses: vec![Sense {
+                pos: PartOfSpeech::Verb,
+                full_def: "to say something out loud".to_string(),
+                short_def: "to say".to_string(),
+            }],
+            ..Default::default()
+        };
+
+        let data = [
+            ("no one uttering a word", 2, "to say"),
+            ("he uttered a cry", 1, "to say"),
+            ("she could not utter it", 3, "to say"),
+            ("it was utter nonsense", 2, "complete and total"),
+            ("an utter fool", 1, "complete and total"),
+            ("utter", 0, "complete and total"),
+        ];
+        for (sentence, i, expected) in data {
+            let words: Vec<&str> = sentence.split_whitespace().collect();
+            assert_eq!(select_sense(&dr, &words, i).short_def, expected, "{}", sentence);
+        }
+    }
+
+    #[test]
+    fn test_tag() {
+        let data = [
+            ("he runs fast", 1, "run", PartOfSpeech::Verb),
+            ("the runs were long", 1, "run", PartOfSpeech::Noun),
+            ("she spoke quickly", 2, "quick", PartOfSpeech::Adverb),
+            ("a bigger house", 1, "big", PartOfSpeech::Adjective),
+            ("it seems odd", 2, "odd", PartOfSpeech::Adjective),
+            ("the bark was loud", 1, "bark", PartOfSpeech::Noun),
+            ("dogs bark", 1, "bark", PartOfSpeech::Unknown),
+        ];
+        for (sentence, i, headword, expected) in data {
+            let words: Vec<&str> = sentence.split_whitespace().collect();
+            assert_eq!(tag(&words, i, headword), expected, "{}", sentence);
+        }
+    }
+}
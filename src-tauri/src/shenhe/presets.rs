@@ -0,0 +1,196 @@
+use super::settings::{ validate_payload, CONFIG_DIR };
+use super::store::{ read_json, store_path, write_json };
+use super::types::Payload;
+use serde::{ Deserialize, Serialize };
+use std::fs::File;
+use std::io::{ BufReader, BufWriter };
+use std::path::{ Path, PathBuf };
+use std::sync::Mutex;
+
+const PRESETS_FILE: &str = "presets.json";
+// the presets page and a job's --preset may change the file at the same time
+static PRESETS_LOCK: Mutex<()> = Mutex::new(());
+
+// a named set of options, "kids A2 ruby", any book can be annotated with.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Preset {
+    pub name: String,
+    // the style, levels and limits, the book is left empty
+    pub options: Payload,
+}
+
+impl Preset {
+    // the options of the preset for `book`.
+    pub fn apply(&self, book: &str) -> Payload {
+        Payload { book: book.to_string(), ..self.options.clone() }
+    }
+
+    fn checked(mut self) -> Result<Self, String> {
+        self.name = self.name.trim().to_string();
+        if self.name.is_empty() {
+            return Err("A preset needs a name.".to_string());
+        }
+        let problems = validate_payload(&mut self.options);
+        if !problems.is_empty() {
+            return Err(format!("{}: {}", self.name, problems.join(", ")));
+        }
+        self.options.book.clear();
+        Ok(self)
+    }
+}
+
+// the presets, kept in a json file in the config dir.
+pub struct Presets {
+    path: PathBuf,
+}
+
+impl Default for Presets {
+    fn default() -> Self {
+        Presets::new(store_path(&CONFIG_DIR, PRESETS_FILE))
+    }
+}
+
+impl Presets {
+    pub fn new(path: PathBuf) -> Self {
+        Presets { path }
+    }
+
+    pub fn list(&self) -> Result<Vec<Preset>, String> {
+        let _lock = PRESETS_LOCK.lock().unwrap();
+        self.load()
+    }
+
+    pub fn get(&self, name: &str) -> Result<Preset, String> {
+        let _lock = PRESETS_LOCK.lock().unwrap();
+        self.load()?
+            .into_iter()
+            .find(|preset| preset.name == name)
+            .ok_or(format!("There is no preset named \"{}\".", name))
+    }
+
+    pub fn create(&self, preset: Preset) -> Result<Preset, String> {
+        let _lock = PRESETS_LOCK.lock().unwrap();
+        self.insert(preset)
+    }
+
+    pub fn rename(&self, name: &str, new_name: &str) -> Result<(), String> {
+        let new_name = new_name.trim();
+        if new_name.is_empty() {
+            return Err("A preset needs a name.".to_string());
+        }
+        let _lock = PRESETS_LOCK.lock().unwrap();
+        let mut presets = self.load()?;
+        if new_name != name && presets.iter().any(|preset| preset.name == new_name) {
+            return Err(format!("There is already a preset named \"{}\".", new_name));
+        }
+        let preset = presets
+            .iter_mut()
+            .find(|preset| preset.name == name)
+            .ok_or(format!("There is no preset named \"{}\".", name))?;
+        preset.name = new_name.to_string();
+        self.save(&presets)
+    }
+
+    pub fn delete(&self, name: &str) -> Result<(), String> {
+        let _lock = PRESETS_LOCK.lock().unwrap();
+        let mut presets = self.load()?;
+        let count = presets.len();
+        presets.retain(|preset| preset.name != name);
+        if presets.len() == count {
+            return Err(format!("There is no preset named \"{}\".", name));
+        }
+        self.save(&presets)
+    }
+
+    // add the preset exported to `path`, a preset with the same name gets a number after its name.
+    pub fn import(&self, path: &Path) -> Result<Preset, String> {
+        let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut preset: Preset = serde_json::from_reader(BufReader::new(file)).map_err(|e|
+            format!("{}: {}", path.display(), e)
+        )?;
+        let _lock = PRESETS_LOCK.lock().unwrap();
+        let presets = self.load()?;
+        preset.name = preset.name.trim().to_string();
+        let name = preset.name.clone();
+        let mut count = 1;
+        while presets.iter().any(|existing| existing.name == preset.name) {
+            count += 1;
+            preset.name = format!("{} ({})", name, count);
+        }
+        self.insert(preset)
+    }
+
+    pub fn export(&self, name: &str, path: &Path) -> Result<(), String> {
+        let preset = self.get(name)?;
+        let file = File::create(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        serde_json::to_writer_pretty(BufWriter::new(file), &preset).map_err(|e| e.to_string())
+    }
+
+    // the lock is held by the caller.
+    fn insert(&self, preset: Preset) -> Result<Preset, String> {
+        let preset = preset.checked()?;
+        let mut presets = self.load()?;
+        if presets.iter().any(|existing| existing.name == preset.name) {
+            return Err(format!("There is already a preset named \"{}\".", preset.name));
+        }
+        presets.push(preset.clone());
+        self.save(&presets)?;
+        Ok(preset)
+    }
+
+    fn load(&self) -> Result<Vec<Preset>, String> {
+        Ok(read_json(&self.path)?.unwrap_or_default())
+    }
+
+    fn save(&self, presets: &[Preset]) -> Result<(), String> {
+        write_json(&self.path, presets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::store::TestDir;
+    use super::*;
+
+    fn preset(name: &str, hint_level: i32) -> Preset {
+        Preset {
+            name: name.to_string(),
+            options: Payload { book: "Dune.epub".to_string(), hint_level, ..Default::default() },
+        }
+    }
+
+    #[test]
+    fn test_presets() {
+        let root = TestDir::new("presets");
+        let presets = Presets::new(root.join(PRESETS_FILE));
+
+        let kids = presets.create(preset(" kids A2 ruby ", 5)).unwrap();
+        assert_eq!(kids.name, "kids A2 ruby");
+        assert_eq!(kids.options.book, "");
+        assert!(presets.create(preset("kids A2 ruby", 4)).is_err());
+        assert!(presets.create(preset("broken", 9)).is_err());
+        presets.create(preset("adult C1", 1)).unwrap();
+
+        let payload = presets.get("kids A2 ruby").unwrap().apply("Emma.epub");
+        assert_eq!((payload.book.as_str(), payload.hint_level), ("Emma.epub", 5));
+
+        assert!(presets.rename("adult C1", "kids A2 ruby").is_err());
+        presets.rename("adult C1", "adult C1 inline").unwrap();
+        assert!(presets.get("adult C1").is_err());
+
+        // export and import it back, under another name
+        let exported = root.join("kids.json");
+        presets.export("kids A2 ruby", &exported).unwrap();
+        assert_eq!(presets.import(&exported).unwrap().name, "kids A2 ruby (2)");
+
+        presets.delete("kids A2 ruby").unwrap();
+        assert!(presets.delete("kids A2 ruby").is_err());
+        let names: Vec<String> = presets
+            .list()
+            .unwrap()
+            .into_iter()
+            .map(|preset| preset.name)
+            .collect();
+        assert_eq!(names, vec!["adult C1 inline", "kids A2 ruby (2)"]);
+    }
+}
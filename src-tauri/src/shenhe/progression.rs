@@ -0,0 +1,251 @@
+use super::store::{ read_json, write_json };
+use super::types::APP_DATA_DIR;
+use serde::{ Deserialize, Serialize };
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+const SERIES_FOLDER: &str = "series";
+
+// a job of a series may end while another one of it is saving, the state is read and written under it
+static SERIES_LOCK: Mutex<()> = Mutex::new(());
+
+// glosses fade as the reader goes through the book, or through the books of a series: the payload's hint level
+// and occurrence limit are the ones at the start, these are the ones at the end.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Progression {
+    pub end_hint_level: i32,
+    // 0 keeps the payload's occurrence limit to the end
+    #[serde(default)]
+    pub end_occurrences: usize,
+    // how many books the fading is spread over, 0 or 1 is this book alone
+    #[serde(default)]
+    pub series_length: usize,
+    // the name the series' state is saved under, book 2 continues where book 1 stopped. empty saves nothing.
+    #[serde(default)]
+    pub series: String,
+}
+
+// what the books of a series already read leave to the next one.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct SeriesState {
+    // in reading order, a book annotated again keeps its place
+    #[serde(default)]
+    pub books: Vec<SeriesBook>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct SeriesBook {
+    // the book's file name
+    pub book: String,
+    // how many times every headword was met in the book, annotated or not
+    pub seen: HashMap<String, usize>,
+}
+
+// where a book starts in its series: the books before it and how many times they met every headword.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SeriesProgress {
+    pub books_done: usize,
+    pub seen: HashMap<String, usize>,
+}
+
+impl SeriesState {
+    // a book annotated again starts where it did the first time.
+    pub fn progress_before(&self, book: &str) -> SeriesProgress {
+        let books_done = self.books
+            .iter()
+            .position(|done| done.book == book)
+            .unwrap_or(self.books.len());
+        let mut seen = HashMap::new();
+        for done in &self.books[..books_done] {
+            for (headword, count) in &done.seen {
+                *seen.entry(headword.clone()).or_insert(0) += count;
+            }
+        }
+        SeriesProgress { books_done, seen }
+    }
+
+    // keep what `book` met going from `start` to `end`, in place of an earlier run of it.
+    pub fn record(&mut self, book: &str, start: &SeriesProgress, end: &SeriesProgress) {
+        let seen = end.seen
+            .iter()
+            .map(|(headword, count)| {
+                (headword.clone(), count.saturating_sub(start.seen.get(headword).copied().unwrap_or(0)))
+            })
+            .filter(|(_, count)| *count > 0)
+            .collect();
+        let done = SeriesBook { book: book.to_string(), seen };
+        match self.books.iter_mut().find(|earlier| earlier.book == book) {
+            Some(earlier) => {
+                *earlier = done;
+            }
+            None => self.books.push(done),
+        }
+    }
+}
+
+// the hint level and occurrence limit at a position of the book, in reading order (calibre writes the html in
+// the spine's order).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fade {
+    // None when the difficulty isn't the hint level, then only the occurrences fade
+    pub hint_levels: Option<(i32, i32)>,
+    pub occurrences: (usize, usize),
+    // the part of the series the book covers, from 0 to 1
+    pub span: (f64, f64),
+}
+
+impl Fade {
+    pub fn new(
+        progression: &Progression,
+        hint_level: Option<i32>,
+        max_occurrences: usize,
+        books_done: usize
+    ) -> Self {
+        let series_length = progression.series_length.max(1) as f64;
+        let from = ((books_done as f64) / series_length).min(1.0);
+        let to = (((books_done + 1) as f64) / series_length).min(1.0);
+        let end_occurrences = if progression.end_occurrences > 0 {
+            progression.end_occurrences
+        } else {
+            max_occurrences
+        };
+        // without a limit at the start the one at the end is kept all along
+        let start_occurrences = if max_occurrences > 0 { max_occurrences } else { end_occurrences };
+
+        Fade {
+            hint_levels: hint_level.map(|hint_level| (hint_level, progression.end_hint_level)),
+            occurrences: (start_occurrences, end_occurrences),
+            span: (from, to),
+        }
+    }
+
+    // the most generous hint level of the fade, the words are annotated with it and dropped as the book goes on
+    pub fn widest_hint_level(&self) -> Option<i32> {
+        self.hint_levels.map(|(start, end)| start.max(end))
+    }
+
+    // `position` in the book, from 0 to 1
+    pub fn hint_level_at(&self, position: f64) -> Option<i32> {
+        self.hint_levels.map(|(start, end)| interpolate(start as f64, end as f64, self.progress(position)) as i32)
+    }
+
+    // 0 means every occurrence
+    pub fn occurrences_at(&self, position: f64) -> usize {
+        let (start, end) = self.occurrences;
+        interpolate(start as f64, end as f64, self.progress(position)) as usize
+    }
+
+    fn progress(&self, position: f64) -> f64 {
+        let (from, to) = self.span;
+        from + (to - from) * position.clamp(0.0, 1.0)
+    }
+}
+
+fn interpolate(start: f64, end: f64, progress: f64) -> f64 {
+    (start + (end - start) * progress).round()
+}
+
+// a series nobody has read yet starts from nothing.
+pub fn load_series(name: &str) -> Result<SeriesState, String> {
+    Ok(read_json(&series_path(name))?.unwrap_or_default())
+}
+
+pub fn save_series(name: &str, state: &SeriesState) -> Result<(), String> {
+    write_json(&series_path(name), state)
+}
+
+// keep what `book` met in the series `name`, see `SeriesState::record`.
+pub fn record_series_book(
+    name: &str,
+    book: &str,
+    start: &SeriesProgress,
+    end: &SeriesProgress
+) -> Result<(), String> {
+    let _lock = SERIES_LOCK.lock().unwrap();
+    let mut state = load_series(name)?;
+    state.record(book, start, end);
+    save_series(name, &state)
+}
+
+fn series_path(name: &str) -> PathBuf {
+    // the name comes from the user, keep it a plain file name
+    let file_name: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' || c == ' ' { c } else { '_' })
+        .collect();
+    let folder = match APP_DATA_DIR.get() {
+        Some(path) => PathBuf::from(path.as_str()),
+        None => std::env::current_dir().unwrap().join("resources"),
+    };
+    folder.join(SERIES_FOLDER).join(format!("{}.json", file_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fade() {
+        let progression = Progression {
+            end_hint_level: 1,
+            end_occurrences: 1,
+            series_length: 2,
+            series: String::new(),
+        };
+
+        // book 1 of 2 goes from hint level 5 to 3 and from 5 occurrences to 3
+        let fade = Fade::new(&progression, Some(5), 5, 0);
+        assert_eq!(fade.widest_hint_level(), Some(5));
+        assert_eq!(fade.hint_level_at(0.0), Some(5));
+        assert_eq!(fade.hint_level_at(1.0), Some(3));
+        assert_eq!(fade.occurrences_at(0.5), 4);
+
+        // book 2 continues from there
+        let fade = Fade::new(&progression, Some(5), 5, 1);
+        assert_eq!(fade.hint_level_at(0.0), Some(3));
+        assert_eq!(fade.hint_level_at(1.0), Some(1));
+        assert_eq!(fade.occurrences_at(1.0), 1);
+
+        // a third book stays at the end of the fade
+        assert_eq!(Fade::new(&progression, Some(5), 5, 2).hint_level_at(0.0), Some(1));
+
+        // without a hint level only the occurrences fade, without a start limit the end one holds
+        let fade = Fade::new(&progression, None, 0, 0);
+        assert_eq!(fade.hint_level_at(0.5), None);
+        assert_eq!(fade.occurrences_at(0.0), 1);
+    }
+
+    #[test]
+    fn test_series_state() {
+        let mut state = SeriesState::default();
+        let start = state.progress_before("one.epub");
+        assert_eq!(start, SeriesProgress::default());
+        let end = SeriesProgress { books_done: 0, seen: HashMap::from([("utter".to_string(), 2)]) };
+        state.record("one.epub", &start, &end);
+
+        let start = state.progress_before("two.epub");
+        assert_eq!(start.books_done, 1);
+        let mut end = start.clone();
+        *end.seen.get_mut("utter").unwrap() += 1;
+        end.seen.insert("sociable".to_string(), 1);
+        state.record("two.epub", &start, &end);
+        assert_eq!(state.books[1].seen, HashMap::from([("utter".to_string(), 1), ("sociable".to_string(), 1)]));
+
+        // annotating book 1 again starts it from nothing and keeps its place
+        assert_eq!(state.progress_before("one.epub"), SeriesProgress::default());
+        let end = SeriesProgress { books_done: 0, seen: HashMap::from([("utter".to_string(), 3)]) };
+        state.record("one.epub", &SeriesProgress::default(), &end);
+        assert_eq!(state.books.len(), 2);
+        assert_eq!(state.progress_before("two.epub").seen.get("utter"), Some(&3));
+        assert_eq!(state.progress_before("three.epub").books_done, 2);
+        assert_eq!(state.progress_before("three.epub").seen.get("utter"), Some(&4));
+    }
+
+    #[test]
+    fn test_series_path() {
+        let path = series_path("../Dune: book/1");
+        assert_eq!(path.file_name().unwrap().to_str().unwrap(), "___Dune_ book_1.json");
+        assert_eq!(path.parent().unwrap().file_name().unwrap(), SERIES_FOLDER);
+    }
+}
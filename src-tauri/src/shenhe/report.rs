@@ -0,0 +1,378 @@
+use super::annotation::{ get_dict_record, ENGLISH };
+use super::names::{ plain_text, BLOCK_BOUNDARY };
+use super::segment::Segmenter;
+use super::types::{ is_punctuation, CefrLevel, DictRecord };
+use csv::Writer;
+use serde::{ Deserialize, Serialize };
+use std::collections::HashMap;
+use std::path::Path;
+
+// how many of the most frequent hard words the report lists.
+const HARD_WORDS: usize = 20;
+// the words at this level and above are the hard ones.
+const HARD_LEVEL: CefrLevel = CefrLevel::C1;
+// the share of the running words a reader has to know to read the book comfortably.
+const COMFORTABLE_COVERAGE: f64 = 0.95;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LevelCoverage {
+    pub hint_lvl: i32,
+    pub words: usize,
+    // the share of the running words, in percent
+    pub percent: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct WordCount {
+    pub lemma: String,
+    pub hint_lvl: i32,
+    pub count: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BookReport {
+    pub total_words: usize,
+    pub unique_lemmas: usize,
+    // the running words found in the dictionary, by hint level
+    pub coverage: Vec<LevelCoverage>,
+    // the running words the dictionary doesn't have, mostly the everyday words and names
+    pub unlisted: LevelCoverage,
+    pub hard_words: Vec<WordCount>,
+    // the level whose words, with the ones below it, make 95% of the running words
+    pub cefr_level: CefrLevel,
+    // the flesch reading ease, only for english books
+    pub readability: Option<f64>,
+}
+
+// tokenise the text of a converted book and look its words up the way they're annotated.
+pub fn analyze(
+    html: &str,
+    dict: &HashMap<String, DictRecord>,
+    lemma: &HashMap<String, String>,
+    segmenter: &Segmenter,
+    book_language: &str
+) -> BookReport {
+    let mut total_words = 0;
+    let mut lemma_counts: HashMap<String, (Option<&DictRecord>, usize)> = HashMap::new();
+    let mut level_words: HashMap<i32, usize> = HashMap::new();
+    let mut cefr_words = [0usize; 6];
+    let mut unlisted_words = 0;
+    let (mut sentences, mut syllables) = (0, 0);
+
+    for block in plain_text(html).split(BLOCK_BOUNDARY) {
+        let mut open_sentence = false;
+        for token in segmenter.segment(block, dict) {
+            let word = token.trim_matches(is_punctuation);
+            if !word.chars().any(char::is_alphabetic) {
+                continue;
+            }
+            total_words += 1;
+            syllables += count_syllables(word);
+            open_sentence = !token.trim_end_matches(['"', '”', '’', ')']).ends_with(['.', '!', '?']);
+            if !open_sentence {
+                sentences += 1;
+            }
+
+            match get_dict_record(word, dict, lemma, book_language) {
+                Some(dr) => {
+                    *level_words.entry(dr.hint_lvl).or_insert(0) += 1;
+                    cefr_words[dr.cefr_level() as usize] += 1;
+                    lemma_counts.entry(dr.word.clone()).or_insert((Some(dr), 0)).1 += 1;
+                }
+                None => {
+                    unlisted_words += 1;
+                    cefr_words[CefrLevel::A1 as usize] += 1;
+                    let lowercase = word.to_lowercase();
+                    let key = lemma.get(&lowercase).cloned().unwrap_or(lowercase);
+                    lemma_counts.entry(key).or_insert((None, 0)).1 += 1;
+                }
+            }
+        }
+        // a heading or a paragraph without a full stop still ends its sentence
+        if open_sentence {
+            sentences += 1;
+        }
+    }
+
+    let mut coverage: Vec<LevelCoverage> = level_words
+        .into_iter()
+        .map(|(hint_lvl, words)| LevelCoverage { hint_lvl, words, percent: percent(words, total_words) })
+        .collect();
+    coverage.sort_by_key(|level| level.hint_lvl);
+
+    let mut hard_words: Vec<WordCount> = lemma_counts
+        .iter()
+        .filter_map(|(lemma, (dr, count))| {
+            dr.filter(|dr| dr.cefr_level() >= HARD_LEVEL).map(|dr| WordCount {
+                lemma: lemma.clone(),
+                hint_lvl: dr.hint_lvl,
+                count: *count,
+            })
+        })
+        .collect();
+    hard_words.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.lemma.cmp(&b.lemma)));
+    hard_words.truncate(HARD_WORDS);
+
+    let readability = if book_language == ENGLISH && total_words > 0 {
+        let words = total_words as f64;
+        Some(
+            206.835 -
+                (1.015 * words) / (sentences.max(1) as f64) -
+                (84.6 * (syllables as f64)) / words
+        )
+    } else {
+        None
+    };
+
+    BookReport {
+        total_words,
+        unique_lemmas: lemma_counts.len(),
+        coverage,
+        unlisted: LevelCoverage {
+            hint_lvl: 0,
+            words: unlisted_words,
+            percent: percent(unlisted_words, total_words),
+        },
+        hard_words,
+        cefr_level: estimate_cefr_level(&cefr_words),
+        readability,
+    }
+}
+
+fn percent(words: usize, total_words: usize) -> f64 {
+    if total_words == 0 {
+        return 0.0;
+    }
+    ((words as f64) * 100.0) / (total_words as f64)
+}
+
+fn estimate_cefr_level(cefr_words: &[usize; 6]) -> CefrLevel {
+    let levels = [
+        CefrLevel::A1,
+        CefrLevel::A2,
+        CefrLevel::B1,
+        CefrLevel::B2,
+        CefrLevel::C1,
+        CefrLevel::C2,
+    ];
+    let total_words: usize = cefr_words.iter().sum();
+    let mut known = 0;
+    for level in levels {
+        known += cefr_words[level as usize];
+        if (known as f64) >= (total_words as f64) * COMFORTABLE_COVERAGE {
+            return level;
+        }
+    }
+    CefrLevel::C2
+}
+
+// the vowel groups of an english word, a final silent e doesn't count.
+fn count_syllables(word: &str) -> usize {
+    let word = word.to_lowercase();
+    let is_vowel = |c: char| matches!(c, 'a' | 'e' | 'i' | 'o' | 'u' | 'y');
+    let mut count = 0;
+    let mut after_vowel = false;
+    for c in word.chars() {
+        let vowel = is_vowel(c);
+        if vowel && !after_vowel {
+            count += 1;
+        }
+        after_vowel = vowel;
+    }
+    // the e of "table" is heard, the one of "versatile" isn't
+    let consonant_le = word
+        .strip_suffix("le")
+        .and_then(|stem| stem.chars().last())
+        .is_some_and(|c| !is_vowel(c));
+    if word.ends_with('e') && !consonant_le && count > 1 {
+        count -= 1;
+    }
+    count.max(1)
+}
+
+// save the report as csv or html, by the extension of `path`.
+pub fn export_report(report: &BookReport, path: &str) -> Result<(), String> {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    let content = match extension.as_str() {
+        "csv" => report_csv(report)?,
+        "html" | "htm" => report_html(report),
+        _ => {
+            return Err(format!("unsupported report format: {}", path));
+        }
+    };
+    std::fs::write(path, content).map_err(|e| e.to_string())
+}
+
+fn report_csv(report: &BookReport) -> Result<String, String> {
+    let mut writer = Writer::from_writer(Vec::new());
+    let mut rows = vec![
+        ["summary".to_string(), "total words".to_string(), report.total_words.to_string(), String::new()],
+        ["summary".to_string(), "unique lemmas".to_string(), report.unique_lemmas.to_string(), String::new()],
+        ["summary".to_string(), "cefr level".to_string(), format!("{:?}", report.cefr_level), String::new()],
+        [
+            "summary".to_string(),
+            "readability".to_string(),
+            report.readability.map_or(String::new(), |score| format!("{:.1}", score)),
+            String::new(),
+        ]
+    ];
+    for level in &report.coverage {
+        rows.push([
+            "coverage".to_string(),
+            format!("hint level {}", level.hint_lvl),
+            level.words.to_string(),
+            format!("{:.2}", level.percent),
+        ]);
+    }
+    rows.push([
+        "coverage".to_string(),
+        "not in dictionary".to_string(),
+        report.unlisted.words.to_string(),
+        format!("{:.2}", report.unlisted.percent),
+    ]);
+    for word in &report.hard_words {
+        rows.push(["hard word".to_string(), word.lemma.clone(), word.count.to_string(), String::new()]);
+    }
+
+    writer.write_record(["section", "name", "value", "percent"]).map_err(|e| e.to_string())?;
+    for row in rows {
+        writer.write_record(&row).map_err(|e| e.to_string())?;
+    }
+    let bytes = writer.into_inner().map_err(|e| e.to_string())?;
+    String::from_utf8(bytes).map_err(|e| e.to_string())
+}
+
+fn report_html(report: &BookReport) -> String {
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Book report</title></head><body>\n"
+    );
+    html.push_str("<h1>Book report</h1>\n<table>\n");
+    html.push_str(&format!("<tr><th>Total words</th><td>{}</td></tr>\n", report.total_words));
+    html.push_str(&format!("<tr><th>Unique lemmas</th><td>{}</td></tr>\n", report.unique_lemmas));
+    html.push_str(&format!("<tr><th>CEFR level</th><td>{:?}</td></tr>\n", report.cefr_level));
+    if let Some(score) = report.readability {
+        html.push_str(&format!("<tr><th>Readability</th><td>{:.1}</td></tr>\n", score));
+    }
+    html.push_str("</table>\n<h2>Coverage</h2>\n<table>\n<tr><th>Hint level</th><th>Words</th><th>%</th></tr>\n");
+    for level in &report.coverage {
+        html.push_str(
+            &format!(
+                "<tr><td>{}</td><td>{}</td><td>{:.2}</td></tr>\n",
+                level.hint_lvl,
+                level.words,
+                level.percent
+            )
+        );
+    }
+    html.push_str(
+        &format!(
+            "<tr><td>not in dictionary</td><td>{}</td><td>{:.2}</td></tr>\n",
+            report.unlisted.words,
+            report.unlisted.percent
+        )
+    );
+    html.push_str("</table>\n<h2>Hard words</h2>\n<table>\n<tr><th>Word</th><th>Hint level</th><th>Count</th></tr>\n");
+    for word in &report.hard_words {
+        html.push_str(
+            &format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                escape_html(&word.lemma),
+                word.hint_lvl,
+                word.count
+            )
+        );
+    }
+    html.push_str("</table>\n</body></html>\n");
+    html
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(word: &str, hint_lvl: i32) -> (String, DictRecord) {
+        (word.to_string(), DictRecord {
+            word: word.to_string(),
+            short_def: format!("def of {}", word),
+            hint_lvl,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_analyze() {
+        let dict: HashMap<String, DictRecord> = [
+            record("ribose", 1),
+            record("versatile", 2),
+            record("sociable", 3),
+            record("happy", 5),
+        ]
+            .into_iter()
+            .collect();
+        let lemma: HashMap<String, String> = [("was".to_string(), "be".to_string())].into_iter().collect();
+        let html =
+            "<html><body><h1>Ribose</h1><p>The versatile ribose was very happy. Ribose was sociable!</p></body></html>";
+
+        let report = analyze(html, &dict, &lemma, &Segmenter::Whitespace, "en");
+        assert_eq!(report.total_words, 10);
+        // ribose, the, versatile, be, very, happy, sociable
+        assert_eq!(report.unique_lemmas, 7);
+        assert_eq!(report.coverage, vec![
+            LevelCoverage { hint_lvl: 1, words: 3, percent: 30.0 },
+            LevelCoverage { hint_lvl: 2, words: 1, percent: 10.0 },
+            LevelCoverage { hint_lvl: 3, words: 1, percent: 10.0 },
+            LevelCoverage { hint_lvl: 5, words: 1, percent: 10.0 },
+        ]);
+        assert_eq!(report.unlisted.words, 4);
+        assert_eq!(report.hard_words, vec![
+            WordCount { lemma: "ribose".to_string(), hint_lvl: 1, count: 3 },
+            WordCount { lemma: "versatile".to_string(), hint_lvl: 2, count: 1 },
+        ]);
+        assert_eq!(report.cefr_level, CefrLevel::C2);
+        assert!(report.readability.is_some());
+
+        assert_eq!(analyze(html, &dict, &lemma, &Segmenter::Whitespace, "de").readability, None);
+    }
+
+    #[test]
+    fn test_estimate_cefr_level() {
+        assert_eq!(estimate_cefr_level(&[90, 3, 2, 3, 2, 0]), CefrLevel::B1);
+        assert_eq!(estimate_cefr_level(&[96, 0, 0, 0, 0, 4]), CefrLevel::A1);
+        assert_eq!(estimate_cefr_level(&[0, 0, 0, 0, 0, 0]), CefrLevel::A1);
+    }
+
+    #[test]
+    fn test_count_syllables() {
+        let data = [("the", 1), ("table", 2), ("versatile", 3), ("happy", 2), ("ribose", 2), ("rhythm", 1)];
+        for (word, expected) in data {
+            assert_eq!(count_syllables(word), expected, "{}", word);
+        }
+    }
+
+    #[test]
+    fn test_report_csv() {
+        let report = BookReport {
+            total_words: 10,
+            unique_lemmas: 6,
+            coverage: vec![LevelCoverage { hint_lvl: 1, words: 3, percent: 30.0 }],
+            unlisted: LevelCoverage { hint_lvl: 0, words: 7, percent: 70.0 },
+            hard_words: vec![WordCount { lemma: "ribose, sugar".to_string(), hint_lvl: 1, count: 3 }],
+            cefr_level: CefrLevel::B2,
+            readability: None,
+        };
+        let csv = report_csv(&report).unwrap();
+        assert!(csv.starts_with("section,name,value,percent\nsummary,total words,10,\n"));
+        assert!(csv.contains("summary,cefr level,B2,\n"));
+        assert!(csv.contains("coverage,hint level 1,3,30.00\n"));
+        assert!(csv.contains("hard word,\"ribose, sugar\",3,\n"));
+        assert!(report_html(&report).contains("<td>ribose, sugar</td>"));
+    }
+}
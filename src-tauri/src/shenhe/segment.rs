@@ -0,0 +1,129 @@
+use super::types::DictRecord;
+use std::collections::HashMap;
+
+// the longest headword maximal matching will try, in characters.
+const MAX_HEADWORD_CHARS: usize = 16;
+
+// how a book's text is cut into words before looking them up.
+pub enum Segmenter {
+    // words are separated by whitespace, english, german and friends.
+    Whitespace,
+    // the script doesn't put spaces between words (chinese, japanese, thai), cut the text into the longest
+    // dictionary headwords found from left to right.
+    MaximalMatching(usize),
+}
+
+impl Segmenter {
+    pub fn new(book_language: &str, dict: &HashMap<String, DictRecord>) -> Self {
+        match book_language {
+            "cn" | "zh" | "jp" | "ja" | "th" => {
+                let max_chars = dict
+                    .keys()
+                    .map(|headword| headword.chars().count())
+                    .max()
+                    .unwrap_or(1)
+                    .clamp(1, MAX_HEADWORD_CHARS);
+                Segmenter::MaximalMatching(max_chars)
+            }
+            _ => Segmenter::Whitespace,
+        }
+    }
+
+    // what goes back between two words when the sentence is put together again.
+    pub fn separator(&self) -> &'static str {
+        match self {
+            Segmenter::Whitespace => " ",
+            Segmenter::MaximalMatching(_) => "",
+        }
+    }
+
+    // cut a sentence into words, the leading and trailing whitespace is not part of any word.
+    pub fn segment<'t>(&self, sentence: &'t str, dict: &HashMap<String, DictRecord>) -> Vec<&'t str> {
+        match self {
+            Segmenter::Whitespace => sentence.split_whitespace().collect(),
+            Segmenter::MaximalMatching(max_chars) => maximal_matching(sentence.trim(), dict, *max_chars),
+        }
+    }
+}
+
+fn maximal_matching<'t>(
+    text: &'t str,
+    dict: &HashMap<String, DictRecord>,
+    max_chars: usize
+) -> Vec<&'t str> {
+    let mut words = Vec::new();
+    let mut rest = text;
+
+    while let Some(first) = rest.chars().next() {
+        let len = if first.is_whitespace() {
+            // keep the spaces so they can be put back as they were
+            rest.find(|c: char| !c.is_whitespace()).unwrap_or(rest.len())
+        } else if first.is_ascii_alphanumeric() {
+            // a latin word in the middle of the text
+            rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '\'' || c == '-')).unwrap_or(
+                rest.len()
+            )
+        } else {
+            rest.char_indices()
+                .map(|(i, c)| i + c.len_utf8())
+                .take(max_chars)
+                .filter(|&end| dict.contains_key(&rest[..end]))
+                .last()
+                .unwrap_or(first.len_utf8())
+        };
+
+        words.push(&rest[..len]);
+        rest = &rest[len..];
+    }
+
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dict(words: &[&str]) -> HashMap<String, DictRecord> {
+        words
+            .iter()
+            .map(|word| {
+                (word.to_string(), DictRecord {
+                    word: word.to_string(),
+                    phoneme: "".to_string(),
+                    full_def: "".to_string(),
+                    short_def: "".to_string(),
+                    example_sentences: "".to_string(),
+                    hint_lvl: 1,
+                    ..Default::default()
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_segment() {
+        let dict = dict(&["喜欢", "一石二鸟", "一石", "办法", "ภาษา", "ไทย"]);
+
+        let segmenter = Segmenter::new("cn", &dict);
+        assert_eq!(segmenter.separator(), "");
+        assert_eq!(
+            segmenter.segment("我喜欢一石二鸟的办法。", &dict),
+            vec!["我", "喜欢", "一石二鸟", "的", "办法", "。"]
+        );
+        assert_eq!(
+            segmenter.segment(" 用iPhone 的办法 ", &dict),
+            vec!["用", "iPhone", " ", "的", "办法"]
+        );
+
+        let segmenter = Segmenter::new("th", &dict);
+        assert_eq!(segmenter.segment("ภาษาไทย", &dict), vec!["ภาษา", "ไทย"]);
+
+        let segmenter = Segmenter::new("en", &dict);
+        assert_eq!(segmenter.separator(), " ");
+        assert_eq!(segmenter.segment(" in  someone's pocket ", &dict), vec![
+            "in",
+            "someone's",
+            "pocket",
+        ]);
+    }
+}
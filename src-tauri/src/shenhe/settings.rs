@@ -0,0 +1,257 @@
+use super::output::check_output_name;
+use super::store::{ read_json, store_path, write_json };
+use super::types::Payload;
+use once_cell::sync::OnceCell;
+use serde::{ Deserialize, Serialize };
+use serde_json::Value;
+use std::path::{ Path, PathBuf };
+
+// the app's config dir, the settings go to the current dir without it.
+pub static CONFIG_DIR: OnceCell<String> = OnceCell::new();
+
+const SETTINGS_FILE: &str = "settings.json";
+// version 1 was `{ "theme": ... }` alone, without a version.
+pub const SETTINGS_VERSION: u32 = 2;
+
+const THEMES: [&str; 2] = ["light", "dark"];
+pub const FORMATS: [&str; 10] = ["epub", "mobi", "pdf", "azw3", "fb2", "docx", "rb", "rtf", "snb", "tcr"];
+const STYLES: std::ops::RangeInclusive<i32> = 0..=2;
+const HINT_LEVELS: std::ops::RangeInclusive<i32> = 1..=5;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct AppSetting {
+    pub version: u32,
+    pub theme: String,
+    // the options the main page starts with, their languages pick the dictionaries. the book is left empty.
+    pub defaults: Payload,
+    // where the annotated books are saved, empty saves them next to the book
+    pub output_dir: String,
+    // calibre's ebook-convert, empty looks for it in the PATH and where calibre is usually installed
+    pub ebook_convert: String,
+}
+
+impl Default for AppSetting {
+    fn default() -> Self {
+        AppSetting {
+            version: SETTINGS_VERSION,
+            theme: THEMES[0].to_string(),
+            defaults: Payload::default(),
+            output_dir: String::new(),
+            ebook_convert: String::new(),
+        }
+    }
+}
+
+impl AppSetting {
+    // put the default back in every field which can't be used, returns what was wrong.
+    pub fn validate(&mut self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let defaults = AppSetting::default();
+        if !THEMES.contains(&self.theme.as_str()) {
+            problems.push(format!("unknown theme \"{}\"", self.theme));
+            self.theme = defaults.theme;
+        }
+        if !self.output_dir.is_empty() && !PathBuf::from(&self.output_dir).is_absolute() {
+            problems.push(format!("the output directory \"{}\" is not an absolute path", self.output_dir));
+            self.output_dir = defaults.output_dir;
+        }
+        if !self.ebook_convert.is_empty() && !PathBuf::from(&self.ebook_convert).is_absolute() {
+            problems.push(format!("the ebook-convert path \"{}\" is not an absolute path", self.ebook_convert));
+            self.ebook_convert = defaults.ebook_convert;
+        }
+        problems.extend(validate_payload(&mut self.defaults));
+        self.version = SETTINGS_VERSION;
+        problems
+    }
+}
+
+// the same for the options of a job.
+pub fn validate_payload(payload: &mut Payload) -> Vec<String> {
+    let mut problems = Vec::new();
+    let defaults = Payload::default();
+    if !FORMATS.contains(&payload.format.as_str()) {
+        problems.push(format!("unknown output format \"{}\"", payload.format));
+        payload.format = defaults.format;
+    }
+    if !is_language(&payload.language) {
+        problems.push(format!("unknown wordwise language \"{}\"", payload.language));
+        payload.language = defaults.language;
+    }
+    if !is_language(&payload.book_language) {
+        problems.push(format!("unknown book language \"{}\"", payload.book_language));
+        payload.book_language = defaults.book_language;
+    }
+    if !HINT_LEVELS.contains(&payload.hint_level) {
+        problems.push(format!("the hint level {} is not between 1 and 5", payload.hint_level));
+        payload.hint_level = payload.hint_level.clamp(*HINT_LEVELS.start(), *HINT_LEVELS.end());
+    }
+    if !STYLES.contains(&payload.wordwise_style) {
+        problems.push(format!("unknown wordwise style {}", payload.wordwise_style));
+        payload.wordwise_style = defaults.wordwise_style;
+    }
+    if payload.max_phrase_length == 0 {
+        problems.push("the longest phrase can't be 0 words".to_string());
+        payload.max_phrase_length = defaults.max_phrase_length;
+    }
+    if !is_color(&payload.annotation_color) {
+        problems.push(format!("\"{}\" is not a color", payload.annotation_color));
+        payload.annotation_color = defaults.annotation_color;
+    }
+    if !payload.output_dir.is_empty() && !PathBuf::from(&payload.output_dir).is_absolute() {
+        problems.push(format!("the output directory \"{}\" is not an absolute path", payload.output_dir));
+        payload.output_dir = defaults.output_dir;
+    }
+    if let Err(problem) = check_output_name(&payload.output_name) {
+        problems.push(problem);
+        payload.output_name = defaults.output_name;
+    }
+    let conversion_problems = payload.conversion.check();
+    if !conversion_problems.is_empty() {
+        problems.extend(conversion_problems);
+        payload.conversion = defaults.conversion;
+    }
+    problems
+}
+
+// "en", "de"... the resource files are named after them.
+fn is_language(language: &str) -> bool {
+    (2..=8).contains(&language.len()) && language.chars().all(|c| c.is_ascii_lowercase() || c == '_')
+}
+
+// a color name or #rgb, it goes into the book's html.
+fn is_color(color: &str) -> bool {
+    match color.strip_prefix('#') {
+        Some(hex) => [3, 6].contains(&hex.len()) && hex.chars().all(|c| c.is_ascii_hexdigit()),
+        None => !color.is_empty() && color.chars().all(|c| c.is_ascii_alphabetic()),
+    }
+}
+
+// bring the settings of an older version up to date.
+fn migrate(mut settings: Value) -> Result<Value, String> {
+    let version = settings
+        .get("version")
+        .and_then(|version| version.as_u64())
+        .unwrap_or(1) as u32;
+    if version > SETTINGS_VERSION {
+        return Err(format!("{} was saved by a newer version of the app", SETTINGS_FILE));
+    }
+    if version < 2 {
+        // the theme was the only setting, everything else starts from the defaults
+        let theme = settings.get("theme").cloned().unwrap_or(Value::Null);
+        settings = serde_json::to_value(AppSetting::default()).map_err(|e| e.to_string())?;
+        if theme.is_string() {
+            settings["theme"] = theme;
+        }
+    }
+    settings["version"] = Value::from(SETTINGS_VERSION);
+    Ok(settings)
+}
+
+fn settings_path() -> PathBuf {
+    store_path(&CONFIG_DIR, SETTINGS_FILE)
+}
+
+// the saved settings, migrated and validated, with what was wrong in them. no file means the defaults.
+pub fn load_settings() -> Result<(AppSetting, Vec<String>), String> {
+    read_settings_file(&settings_path())
+}
+
+fn read_settings_file(path: &Path) -> Result<(AppSetting, Vec<String>), String> {
+    let Some(settings) = read_json::<Value>(path)? else {
+        return Ok((AppSetting::default(), Vec::new()));
+    };
+    let settings = migrate(settings)?;
+    let mut settings: AppSetting = serde_json::from_value(settings).map_err(|e|
+        format!("{}: {}", SETTINGS_FILE, e)
+    )?;
+    let problems = settings.validate();
+    Ok((settings, problems))
+}
+
+pub fn save_settings(settings: &AppSetting) -> Result<(), String> {
+    write_settings_file(&settings_path(), settings)
+}
+
+fn write_settings_file(path: &Path, settings: &AppSetting) -> Result<(), String> {
+    let mut settings = settings.clone();
+    let problems = settings.validate();
+    if !problems.is_empty() {
+        return Err(problems.join(", "));
+    }
+    // the defaults are for any book
+    settings.defaults.book.clear();
+    write_json(path, &settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::store::TestDir;
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_settings() {
+        let root = TestDir::new("settings-save");
+        fs::create_dir_all(&root).unwrap();
+        let path = root.join(SETTINGS_FILE);
+        assert_eq!(read_settings_file(&path).unwrap(), (AppSetting::default(), Vec::new()));
+
+        let mut settings = AppSetting {
+            theme: "dark".to_string(),
+            defaults: Payload {
+                book: "Dune.epub".to_string(),
+                hint_level: 5,
+                annotation_color: "#0a0".to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        write_settings_file(&path, &settings).unwrap();
+
+        let (read, problems) = read_settings_file(&path).unwrap();
+        assert!(problems.is_empty());
+        assert_eq!(read.theme, "dark");
+        assert_eq!(read.defaults.hint_level, 5);
+        assert_eq!(read.defaults.book, "");
+
+        settings.defaults.annotation_color = "red'><script>".to_string();
+        assert!(write_settings_file(&path, &settings).is_err());
+    }
+
+    #[test]
+    fn test_migrate() {
+        let root = TestDir::new("settings-migrate");
+        fs::create_dir_all(&root).unwrap();
+        let path = root.join(SETTINGS_FILE);
+        fs::write(&path, r#"{"theme":"dark"}"#).unwrap();
+        let (settings, problems) = read_settings_file(&path).unwrap();
+        assert!(problems.is_empty());
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert_eq!(settings.theme, "dark");
+        assert_eq!(settings.defaults, Payload::default());
+
+        fs::write(&path, r#"{"version":99}"#).unwrap();
+        assert!(read_settings_file(&path).is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        let root = TestDir::new("settings-validate");
+        fs::create_dir_all(&root).unwrap();
+        let path = root.join(SETTINGS_FILE);
+        fs::write(
+            &path,
+            r#"{"version":2,"theme":"pink","output_dir":"books","defaults":{"book":"","format":"exe","language":"en","hint_level":9,"allow_long":false,"show_phoneme":false,"wordwise_style":0}}"#
+        ).unwrap();
+        let (settings, problems) = read_settings_file(&path).unwrap();
+        assert_eq!(problems.len(), 4);
+        assert_eq!(settings.theme, "light");
+        assert_eq!(settings.output_dir, "");
+        assert_eq!(settings.defaults.format, "epub");
+        assert_eq!(settings.defaults.hint_level, 5);
+
+        assert!(is_color("DarkRed") && is_color("#a0b0c0") && !is_color("#12") && !is_color("red;x"));
+        assert!(is_language("de") && is_language("pt_br") && !is_language("../en"));
+    }
+}
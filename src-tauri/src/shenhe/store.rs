@@ -0,0 +1,108 @@
+use once_cell::sync::OnceCell;
+use serde::{ de::DeserializeOwned, Serialize };
+use std::fs::{ self, File };
+use std::io::{ BufReader, BufWriter, ErrorKind };
+use std::path::{ Path, PathBuf };
+
+// `file_name` in the dir the app set at startup, the current dir without it.
+pub fn store_path(dir: &OnceCell<String>, file_name: &str) -> PathBuf {
+    let folder = match dir.get() {
+        Some(path) => PathBuf::from(path.as_str()),
+        None => std::env::current_dir().unwrap(),
+    };
+    folder.join(file_name)
+}
+
+// None when the file isn't there yet.
+pub fn read_json<T: DeserializeOwned>(path: &Path) -> Result<Option<T>, String> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            return Ok(None);
+        }
+        Err(e) => {
+            return Err(e.to_string());
+        }
+    };
+    serde_json
+        ::from_reader(BufReader::new(file))
+        .map(Some)
+        .map_err(|e| format!("{}: {}", file_name(path), e))
+}
+
+// written aside and renamed over the old file, a crash leaves one or the other whole.
+pub fn write_json<T: Serialize + ?Sized>(path: &Path, value: &T) -> Result<(), String> {
+    if let Some(folder) = path.parent() {
+        fs::create_dir_all(folder).map_err(|e| e.to_string())?;
+    }
+    let temp = path.with_extension("json.tmp");
+    let file = File::create(&temp).map_err(|e| e.to_string())?;
+    serde_json::to_writer_pretty(BufWriter::new(file), value).map_err(|e| e.to_string())?;
+    fs::rename(&temp, path).map_err(|e| e.to_string())
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+// a folder of the temp dir for a test, empty at the start and removed when dropped.
+#[cfg(test)]
+pub struct TestDir {
+    path: PathBuf,
+}
+
+#[cfg(test)]
+impl TestDir {
+    // not created, some tests check the folder is made for them
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("ebook-wordwise-test-{}", name));
+        fs::remove_dir_all(&path).ok();
+        TestDir { path }
+    }
+}
+
+#[cfg(test)]
+impl std::ops::Deref for TestDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[cfg(test)]
+impl AsRef<Path> for TestDir {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        fs::remove_dir_all(&self.path).ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_json() {
+        let root = TestDir::new("store");
+        let path = root.join("counts.json");
+        assert_eq!(read_json::<HashMap<String, usize>>(&path).unwrap(), None);
+
+        let counts = HashMap::from([("utter".to_string(), 2)]);
+        write_json(&path, &counts).unwrap();
+        assert_eq!(read_json(&path).unwrap(), Some(counts));
+        assert!(!path.with_extension("json.tmp").exists());
+
+        fs::write(&path, "{").unwrap();
+        assert!(read_json::<HashMap<String, usize>>(&path).unwrap_err().starts_with("counts.json: "));
+    }
+}
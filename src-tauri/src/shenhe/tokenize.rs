@@ -0,0 +1,143 @@
+use super::types::is_punctuation;
+
+const DASHES: [&str; 3] = ["—", "–", "--"];
+
+// contractions and the possessive, with the word to look up when the part before them isn't a word by itself.
+// the token's curly apostrophes are straightened before they're compared.
+const CLITICS: [(&str, Option<&str>); 10] = [
+    ("won't", Some("will")),
+    ("can't", Some("can")),
+    ("shan't", Some("shall")),
+    ("n't", None),
+    ("'s", None),
+    ("'re", None),
+    ("'ve", None),
+    ("'ll", None),
+    ("'d", None),
+    ("'m", None),
+];
+
+// cut a token at its em dashes, en dashes and double hyphens, keeping the dashes as pieces of their own:
+// "word—another" gives ["word", "—", "another"]. a token without a dash gives nothing.
+pub fn split_dashes(token: &str) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut rest = token;
+
+    while
+        let Some((at, dash)) = DASHES.iter()
+            .filter_map(|dash| rest.find(dash).map(|at| (at, *dash)))
+            .min_by_key(|(at, _)| *at)
+    {
+        if at > 0 {
+            pieces.push(&rest[..at]);
+        }
+        pieces.push(&rest[at..at + dash.len()]);
+        rest = &rest[at + dash.len()..];
+    }
+
+    if pieces.is_empty() {
+        return pieces;
+    }
+    if !rest.is_empty() {
+        pieces.push(rest);
+    }
+    pieces
+}
+
+pub fn is_dash(piece: &str) -> bool {
+    DASHES.contains(&piece)
+}
+
+// find a contraction or possessive at the end of a token, "teacher's," or "isn't".
+// returns where the word to annotate ends in the token, and the word to look up instead when
+// the contraction can't be cut ("won't" is annotated as a whole with the meaning of "will").
+pub fn split_clitic(token: &str) -> Option<(usize, Option<&'static str>)> {
+    let body = token.trim_end_matches(is_punctuation);
+    if !body.contains(['\'', '’']) {
+        return None;
+    }
+    let lowercase = body.to_lowercase().replace('’', "'");
+
+    for (clitic, lookup) in CLITICS {
+        if !lowercase.ends_with(clitic) {
+            continue;
+        }
+        if lookup.is_some() {
+            return Some((body.len(), lookup));
+        }
+
+        // the clitic's length in the token, the apostrophe may be the 3 byte curly one
+        let (at, _) = body.char_indices().rev().nth(clitic.chars().count() - 1)?;
+        let core = token[..at].trim_start_matches(is_punctuation);
+        if core.is_empty() {
+            return None;
+        }
+        return Some((at, None));
+    }
+
+    None
+}
+
+// the parts of a hyphenated compound, "well-known" gives ["well", "-", "known"]. the hyphens
+// at the edges of a token don't count, nothing is returned when there's no hyphen inside.
+pub fn split_hyphens(token: &str) -> Vec<&str> {
+    let start = token.len() - token.trim_start_matches(is_punctuation).len();
+    let end = token.trim_end_matches(is_punctuation).len();
+    if start >= end || !token[start..end].contains('-') {
+        return Vec::new();
+    }
+
+    let mut pieces = Vec::new();
+    let mut last = 0;
+    for (at, _) in token[start..end].match_indices('-') {
+        let at = start + at;
+        pieces.push(&token[last..at]);
+        pieces.push("-");
+        last = at + 1;
+    }
+    pieces.push(&token[last..]);
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_dashes() {
+        assert_eq!(split_dashes("word—another,"), vec!["word", "—", "another,"]);
+        assert_eq!(split_dashes("“well–then--now"), vec!["“well", "–", "then", "--", "now"]);
+        assert_eq!(split_dashes("—quiet"), vec!["—", "quiet"]);
+        assert!(split_dashes("well-being").is_empty());
+    }
+
+    #[test]
+    fn test_split_clitic() {
+        let data = [
+            ("teacher's,", Some((7, None)), "teacher"),
+            ("(Teacher’s)", Some((8, None)), "(Teacher"),
+            ("isn't", Some((2, None)), "is"),
+            ("shouldn’t.", Some((6, None)), "should"),
+            ("won't", Some((5, Some("will"))), "won't"),
+            ("Can’t!", Some((7, Some("can"))), "Can’t"),
+            ("we've", Some((2, None)), "we"),
+            ("o'clock", None, ""),
+            ("'s", None, ""),
+        ];
+        for (token, expected, core) in data {
+            let result = split_clitic(token);
+            assert_eq!(result, expected, "{}", token);
+            if let Some((core_end, _)) = result {
+                assert_eq!(&token[..core_end], core);
+            }
+        }
+    }
+
+    #[test]
+    fn test_split_hyphens() {
+        assert_eq!(split_hyphens("well-being."), vec!["well", "-", "being."]);
+        assert_eq!(split_hyphens("(mother-in-law)"), vec!["(mother", "-", "in", "-", "law)"]);
+        assert!(split_hyphens("-well").is_empty());
+        assert!(split_hyphens("well").is_empty());
+    }
+}
@@ -1,9 +1,85 @@
+use super::annotation::ENGLISH;
+use super::conversion::{ is_kindle, ConversionOptions };
+use super::job::CancelToken;
+use super::occurrence::{ DensityLimit, OccurrenceLimit };
+use super::output::{ OverwritePolicy, DEFAULT_OUTPUT_NAME };
+use super::phrase::{ PhraseMatcher, DEFAULT_MAX_PHRASE_LENGTH };
+use super::progression::{ Fade, Progression };
+use super::segment::Segmenter;
 use once_cell::sync::OnceCell;
 use serde::{ Deserialize, Serialize };
+use std::borrow::Cow;
 use std::collections::{ HashMap, HashSet };
 use tauri::Runtime;
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum PartOfSpeech {
+    #[default]
+    Unknown,
+    Noun,
+    Verb,
+    Adjective,
+    Adverb,
+}
+
+impl PartOfSpeech {
+    pub fn parse(pos: &str) -> Self {
+        match pos.trim().to_lowercase().as_str() {
+            "n" | "noun" => PartOfSpeech::Noun,
+            "v" | "verb" => PartOfSpeech::Verb,
+            "adj" | "adjective" => PartOfSpeech::Adjective,
+            "adv" | "adverb" => PartOfSpeech::Adverb,
+            _ => PartOfSpeech::Unknown,
+        }
+    }
+}
+
+// the common european framework levels, from the beginner's A1 to the proficient C2
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CefrLevel {
+    A1,
+    A2,
+    B1,
+    B2,
+    C1,
+    C2,
+}
+
+impl CefrLevel {
+    pub fn parse(level: &str) -> Option<Self> {
+        match level.trim().to_uppercase().as_str() {
+            "A1" => Some(CefrLevel::A1),
+            "A2" => Some(CefrLevel::A2),
+            "B1" => Some(CefrLevel::B1),
+            "B2" => Some(CefrLevel::B2),
+            "C1" => Some(CefrLevel::C1),
+            "C2" => Some(CefrLevel::C2),
+            _ => None,
+        }
+    }
+
+    // the level of a word which only has a hint level, hint level 1 words are the rarest
+    pub fn from_hint_lvl(hint_lvl: i32) -> Self {
+        match hint_lvl {
+            ..=1 => CefrLevel::C2,
+            2 => CefrLevel::C1,
+            3 => CefrLevel::B2,
+            4 => CefrLevel::B1,
+            5 => CefrLevel::A2,
+            _ => CefrLevel::A1,
+        }
+    }
+}
+
+// another meaning of a headword, for another part of speech
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Sense {
+    pub pos: PartOfSpeech,
+    pub full_def: String,
+    pub short_def: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct DictRecord {
     pub word: String,
     pub phoneme: String,
@@ -11,25 +87,63 @@ pub struct DictRecord {
     pub short_def: String,
     pub example_sentences: String,
     pub hint_lvl: i32,
+    // the part of speech of full_def and short_def
+    #[serde(default)]
+    pub pos: PartOfSpeech,
+    #[serde(default)]
+    pub senses: Vec<Sense>,
+    // the position of the headword in the language's word frequency list, 1 is the most frequent word
+    #[serde(default)]
+    pub frequency_rank: Option<usize>,
+    #[serde(default)]
+    pub cefr: Option<CefrLevel>,
 }
 
 impl DictRecord {
+    // the record with the definitions of the sense for `pos`, the first sense if there's none.
+    pub fn for_pos(&self, pos: PartOfSpeech) -> Cow<'_, DictRecord> {
+        if pos == PartOfSpeech::Unknown || pos == self.pos {
+            return Cow::Borrowed(self);
+        }
+        match self.senses.iter().find(|sense| sense.pos == pos) {
+            Some(sense) =>
+                Cow::Owned(DictRecord {
+                    word: self.word.clone(),
+                    phoneme: self.phoneme.clone(),
+                    full_def: sense.full_def.clone(),
+                    short_def: sense.short_def.clone(),
+                    example_sentences: self.example_sentences.clone(),
+                    hint_lvl: self.hint_lvl,
+                    pos: sense.pos,
+                    senses: Vec::new(),
+                    frequency_rank: self.frequency_rank,
+                    cefr: self.cefr,
+                }),
+            None => Cow::Borrowed(self),
+        }
+    }
+
+    pub fn cefr_level(&self) -> CefrLevel {
+        self.cefr.unwrap_or_else(|| CefrLevel::from_hint_lvl(self.hint_lvl))
+    }
+
     pub fn get_meaning(
         &self,
         def_length: i32,
-        user_hint_lvl: i32,
+        difficulty: Difficulty,
         user_show_phoneme: bool
     ) -> String {
         let mut definition = String::new();
+        let wanted = difficulty.wants(self);
         if
-            (user_hint_lvl == 0 || user_hint_lvl >= self.hint_lvl) &&
+            (difficulty == Difficulty::HintLevel(0) || wanted) &&
             user_show_phoneme &&
             !self.phoneme.is_empty()
         {
             definition += &self.phoneme;
         }
 
-        if user_hint_lvl >= self.hint_lvl {
+        if wanted {
             if def_length == 1 {
                 definition += &format!(" {}", self.short_def);
             } else if def_length == 2 {
@@ -41,41 +155,106 @@ impl DictRecord {
     }
 }
 
+pub fn is_punctuation(c: char) -> bool {
+    matches!(
+        c,
+        ' ' |
+            '`' |
+            '…' |
+            '*' |
+            '•' |
+            '.' |
+            '?' |
+            '!' |
+            '“' |
+            '”' |
+            '‘' |
+            '’' |
+            '"' |
+            ',' |
+            ':' |
+            ';' |
+            '(' |
+            ')' |
+            '[' |
+            ']' |
+            '{' |
+            '}' |
+            '<' |
+            '>' |
+            '\'' |
+            '-' |
+            '&' |
+            '#' |
+            '~'
+    )
+}
+
 pub trait Clean {
     fn clean_word(word: &str, lowercase: bool) -> (String, String, String);
 }
 pub struct Cleaner {}
 impl Clean for Cleaner {
     fn clean_word(word: &str, lowercase: bool) -> (String, String, String) {
-        let punctuation: HashSet<char> = " `…*•.?!“”‘’\",:;()[]{}<>'-&#~".chars().collect();
-
-        let cleaned_word = word.trim_matches(|c: char| punctuation.contains(&c));
-        let prefix = word
-            .chars()
-            .take_while(|c| punctuation.contains(c))
-            .collect::<String>();
-        let suffix = word
-            .chars()
-            .rev()
-            .take_while(|c| punctuation.contains(c))
-            .collect::<String>();
+        let cleaned_word = word.trim_matches(is_punctuation);
+        let prefix = &word[..word.len() - word.trim_start_matches(is_punctuation).len()];
+        let suffix = &word[word.trim_end_matches(is_punctuation).len()..];
         //println!("{} -> {}", word, cleaned_word);
         if lowercase {
-            (
-                cleaned_word.to_lowercase(),
-                prefix.to_string(),
-                suffix.chars().rev().collect::<String>(),
-            )
+            (cleaned_word.to_lowercase(), prefix.to_string(), suffix.to_string())
         } else {
-            (cleaned_word.to_string(), prefix.to_string(), suffix.chars().rev().collect::<String>())
+            (cleaned_word.to_string(), prefix.to_string(), suffix.to_string())
+        }
+    }
+}
+
+// which words are hard enough to be annotated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Difficulty {
+    // the words whose hint level is at most this one
+    HintLevel(i32),
+    // the words which aren't among the language's n most frequent words
+    OutsideTop(usize),
+    // the words above the reader's level
+    AboveCefr(CefrLevel),
+}
+
+impl Difficulty {
+    pub fn wants(&self, dr: &DictRecord) -> bool {
+        match self {
+            Difficulty::HintLevel(hint_lvl) => *hint_lvl >= dr.hint_lvl,
+            // a word missing from the frequency list is rarer than all of them
+            Difficulty::OutsideTop(top) => !matches!(dr.frequency_rank, Some(rank) if rank <= *top),
+            Difficulty::AboveCefr(level) => dr.cefr_level() > *level,
+        }
+    }
+
+    // how rare the word is compared to the other annotated ones, the smaller the rarer.
+    pub fn rarity_rank(&self, dr: &DictRecord) -> i32 {
+        match self {
+            // hint level 1 words are annotated even with the fewest hints, they're the rarest
+            Difficulty::HintLevel(_) => dr.hint_lvl,
+            Difficulty::OutsideTop(_) =>
+                dr.frequency_rank.map_or(i32::MIN, |rank| -i32::try_from(rank).unwrap_or(i32::MAX)),
+            Difficulty::AboveCefr(_) => -(dr.cefr_level() as i32),
         }
     }
 }
 
 pub enum Annotator<'a> {
-    RubyAnnotator(i32, bool),
-    ColorAnnotator(&'a str, i32, bool),
-    InlineAnnotator(i32, bool),
+    RubyAnnotator(Difficulty, bool),
+    ColorAnnotator(&'a str, Difficulty, bool),
+    InlineAnnotator(Difficulty, bool),
+}
+
+impl Annotator<'_> {
+    pub fn difficulty(&self) -> Difficulty {
+        match self {
+            Annotator::RubyAnnotator(difficulty, _) => *difficulty,
+            Annotator::ColorAnnotator(_, difficulty, _) => *difficulty,
+            Annotator::InlineAnnotator(difficulty, _) => *difficulty,
+        }
+    }
 }
 
 pub fn annotate_text(
@@ -85,9 +264,9 @@ pub fn annotate_text(
     def_length: i32
 ) -> String {
     match annotator {
-        Annotator::RubyAnnotator(hint_lvl, phoneme) => {
+        Annotator::RubyAnnotator(difficulty, phoneme) => {
             let (clean_word, prefix, suffix) = Cleaner::clean_word(target, false);
-            let meaning = dr.get_meaning(def_length, *hint_lvl, *phoneme);
+            let meaning = dr.get_meaning(def_length, *difficulty, *phoneme);
             if meaning.len() > 0 {
                 let update = format!(
                     "{}<ruby>{}<rt>{}</rt></ruby>{}",
@@ -101,7 +280,7 @@ pub fn annotate_text(
 
             target.to_string()
         }
-        Annotator::ColorAnnotator(color, _hint_lvl, _phoneme) => {
+        Annotator::ColorAnnotator(color, _difficulty, _phoneme) => {
             let (clean_word, prefix, suffix) = Cleaner::clean_word(target, false);
             if clean_word.len() > 0 {
                 let update = format!(
@@ -114,9 +293,9 @@ pub fn annotate_text(
             }
             target.to_string()
         }
-        Annotator::InlineAnnotator(hint_lvl, phoneme) => {
+        Annotator::InlineAnnotator(difficulty, phoneme) => {
             let (clean_word, prefix, suffix) = Cleaner::clean_word(target, false);
-            let meaning = dr.get_meaning(def_length, *hint_lvl, *phoneme);
+            let meaning = dr.get_meaning(def_length, *difficulty, *phoneme);
             if meaning.len() > 0 {
                 let update = format!(
                     "{}{}<span style='font-size:smaller;color:gray'> [{}]</span>{}",
@@ -132,20 +311,184 @@ pub fn annotate_text(
     }
 }
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 pub struct Payload {
     pub book: String,
     pub format: String,
     pub language: String,
+    #[serde(default = "default_book_language")]
+    pub book_language: String,
     pub hint_level: i32,
     pub allow_long: bool,
     pub show_phoneme: bool,
     pub wordwise_style: i32,
+    #[serde(default = "default_max_phrase_length")]
+    pub max_phrase_length: usize,
+    #[serde(default = "default_detect_names")]
+    pub detect_names: bool,
+    // names the user wants left alone on top of the detected ones
+    #[serde(default)]
+    pub excluded_names: Vec<String>,
+    // detected names the user says are ordinary words
+    #[serde(default)]
+    pub included_names: Vec<String>,
+    // annotate only the first occurrences of a word, 0 annotates all of them
+    #[serde(default)]
+    pub max_occurrences: usize,
+    // count the occurrences per chapter instead of per book
+    #[serde(default)]
+    pub per_chapter: bool,
+    // at most this many annotations per paragraph, the rarest words first, 0 means no limit
+    #[serde(default)]
+    pub max_density: usize,
+    // count the annotations per this many words instead of per paragraph
+    #[serde(default)]
+    pub density_words: usize,
+    // annotate the words outside the language's n most frequent words instead of using the hint level, 0 uses
+    // the hint level
+    #[serde(default)]
+    pub frequency_top: usize,
+    // annotate the words above the reader's level, it goes before the frequency list and the hint level
+    #[serde(default)]
+    pub cefr_level: Option<CefrLevel>,
+    // fade the glosses as the book, or the series, goes on
+    #[serde(default)]
+    pub progression: Option<Progression>,
+    // the color of the annotations in the color style
+    #[serde(default = "default_annotation_color")]
+    pub annotation_color: String,
+    // where the annotated book is saved, empty uses the settings' folder or the book's own folder
+    #[serde(default)]
+    pub output_dir: String,
+    // the annotated book's name, see output::DEFAULT_OUTPUT_NAME
+    #[serde(default = "default_output_name")]
+    pub output_name: String,
+    #[serde(default)]
+    pub overwrite: OverwritePolicy,
+    // calibre's options for the annotated book
+    #[serde(default)]
+    pub conversion: ConversionOptions,
+}
+
+// the options the app starts with.
+impl Default for Payload {
+    fn default() -> Self {
+        Payload {
+            book: String::new(),
+            format: "epub".to_string(),
+            language: ENGLISH.to_string(),
+            book_language: default_book_language(),
+            hint_level: 3,
+            allow_long: false,
+            show_phoneme: false,
+            wordwise_style: 0,
+            max_phrase_length: default_max_phrase_length(),
+            detect_names: default_detect_names(),
+            excluded_names: Vec::new(),
+            included_names: Vec::new(),
+            max_occurrences: 0,
+            per_chapter: false,
+            max_density: 0,
+            density_words: 0,
+            frequency_top: 0,
+            cefr_level: None,
+            progression: None,
+            annotation_color: default_annotation_color(),
+            output_dir: String::new(),
+            output_name: default_output_name(),
+            overwrite: OverwritePolicy::default(),
+            conversion: ConversionOptions::default(),
+        }
+    }
+}
+
+impl Payload {
+    pub fn occurrence_limit(&self) -> OccurrenceLimit {
+        OccurrenceLimit {
+            max: self.max_occurrences,
+            per_chapter: self.per_chapter,
+        }
+    }
+
+    pub fn difficulty(&self) -> Difficulty {
+        if let Some(level) = self.cefr_level {
+            Difficulty::AboveCefr(level)
+        } else if self.frequency_top > 0 {
+            Difficulty::OutsideTop(self.frequency_top)
+        } else {
+            // the fade drops the words it no longer wants, the others have to be annotated first
+            let fade = self.fade(0);
+            Difficulty::HintLevel(
+                fade.and_then(|fade| fade.widest_hint_level()).unwrap_or(self.hint_level)
+            )
+        }
+    }
+
+    // the wordwise style the book gets, a kindle book is annotated inline unless the user keeps the ruby.
+    pub fn style(&self) -> i32 {
+        if self.wordwise_style == 1 && is_kindle(&self.format) && !self.conversion.keep_ruby {
+            0
+        } else {
+            self.wordwise_style
+        }
+    }
+
+    // the name the series' state is saved under, None when the book isn't part of one
+    pub fn series(&self) -> Option<&str> {
+        self.progression
+            .as_ref()
+            .map(|progression| progression.series.as_str())
+            .filter(|name| !name.is_empty())
+    }
+
+    // the fade of the book after `books_done` books of its series
+    pub fn fade(&self, books_done: usize) -> Option<Fade> {
+        let hint_level = if self.cefr_level.is_none() && self.frequency_top == 0 {
+            Some(self.hint_level)
+        } else {
+            None
+        };
+        self.progression
+            .as_ref()
+            .map(|progression| Fade::new(progression, hint_level, self.max_occurrences, books_done))
+    }
+
+    pub fn density_limit(&self) -> DensityLimit {
+        DensityLimit {
+            max: self.max_density,
+            words: self.density_words,
+        }
+    }
+}
+
+fn default_detect_names() -> bool {
+    true
+}
+
+fn default_book_language() -> String {
+    ENGLISH.to_string()
+}
+
+fn default_max_phrase_length() -> usize {
+    DEFAULT_MAX_PHRASE_LENGTH
+}
+
+fn default_annotation_color() -> String {
+    "red".to_string()
+}
+
+fn default_output_name() -> String {
+    DEFAULT_OUTPUT_NAME.to_string()
 }
 
 pub struct ProgressReporter<'a, R: Runtime> {
     progress_fn: fn(f32, &tauri::Window<R>),
     pub tauri_window: &'a tauri::Window<R>,
+    cancel: Option<CancelToken>,
+    // the part of the whole job this reporter's 0 to 1 stands for
+    band: (f32, f32),
+    // send the converter's output to the UI
+    log: bool,
 }
 
 impl<'a, R: Runtime> ProgressReporter<'a, R> {
@@ -156,11 +499,58 @@ impl<'a, R: Runtime> ProgressReporter<'a, R> {
         Self {
             progress_fn,
             tauri_window,
+            cancel: None,
+            band: (0.0, 1.0),
+            log: false,
         }
     }
 
+    // a reporter for a step of the job, its 0 to 1 is `start` to `end` of this one.
+    pub fn within(&self, start: f32, end: f32) -> Self {
+        let (from, to) = self.band;
+        Self {
+            progress_fn: self.progress_fn,
+            tauri_window: self.tauri_window,
+            cancel: self.cancel.clone(),
+            band: (from + (to - from) * start, from + (to - from) * end),
+            log: self.log,
+        }
+    }
+
+    pub fn with_log(mut self) -> Self {
+        self.log = true;
+        self
+    }
+
+    // the job reporting its progress can be cancelled with `token`
+    pub fn with_cancel(mut self, token: CancelToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
     pub fn report(&self, progress: f32) {
-        (self.progress_fn)(progress, &self.tauri_window);
+        let (start, end) = self.band;
+        (self.progress_fn)(start + (end - start) * progress.clamp(0.0, 1.0), &self.tauri_window);
+    }
+
+    // a line of the converter's output, the UI scrolls through them.
+    pub fn log(&self, line: &str) {
+        if self.log {
+            self.tauri_window
+                .emit("event-workmesg", WorkMesg::new("text-gray-600 dark:text-gray-400", line))
+                .ok();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(|token| token.is_cancelled())
+    }
+
+    pub fn check_cancelled(&self) -> Result<(), String> {
+        match &self.cancel {
+            Some(token) => token.check(),
+            None => Ok(()),
+        }
     }
 }
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -178,10 +568,50 @@ impl<'a> WorkMesg<'a> {
 pub struct ChunkParameter<'a> {
     pub dict: &'a HashMap<String, DictRecord>,
     pub lemma: &'a HashMap<String, String>,
+    pub matcher: &'a PhraseMatcher,
+    pub segmenter: &'a Segmenter,
+    // lowercase names which are not annotated when they're capitalised
+    pub excluded: &'a HashSet<String>,
+    pub book_language: &'a str,
     pub def_length: i32,
+    pub occurrence_limit: OccurrenceLimit,
+    pub density_limit: DensityLimit,
+    pub fade: Option<Fade>,
 
     pub annotator: &'a Annotator<'a>,
 }
+
+impl ChunkParameter<'_> {
+    // whether the annotations are written as occurrences for resolve_occurrences
+    pub fn limits_occurrences(&self) -> bool {
+        self.occurrence_limit.is_limited() || self.density_limit.is_limited() || self.fade.is_some()
+    }
+}
+
+// the parameters most tests annotate with: english split on whitespace, no names left out and no limits.
+#[cfg(test)]
+pub fn test_param<'a>(
+    dict: &'a HashMap<String, DictRecord>,
+    lemma: &'a HashMap<String, String>,
+    matcher: &'a PhraseMatcher,
+    annotator: &'a Annotator<'a>
+) -> ChunkParameter<'a> {
+    static NO_NAMES: once_cell::sync::Lazy<HashSet<String>> = once_cell::sync::Lazy::new(HashSet::new);
+    ChunkParameter {
+        dict,
+        lemma,
+        matcher,
+        segmenter: &Segmenter::Whitespace,
+        excluded: &NO_NAMES,
+        book_language: "en",
+        def_length: 1,
+        occurrence_limit: OccurrenceLimit::default(),
+        density_limit: DensityLimit::default(),
+        fade: None,
+        annotator,
+    }
+}
+
 pub type ProcessChunkFn = fn(input: &str, param: &ChunkParameter) -> String;
 
 pub static APP_DATA_DIR: OnceCell<String> = OnceCell::new();
@@ -0,0 +1,147 @@
+use once_cell::sync::OnceCell;
+use std::fs;
+use std::path::{ Path, PathBuf };
+use std::time::{ Duration, SystemTime };
+use uuid::Uuid;
+
+// the app's cache dir, the workspaces go to the system temp dir without it.
+pub static CACHE_DIR: OnceCell<String> = OnceCell::new();
+
+const WORKSPACE_FOLDER: &str = "ebook-wordwise";
+// no conversion takes that long, a workspace this old was left by a crashed run.
+pub const ORPHAN_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+// the folder a job keeps its intermediate files in, away from the user's books. it's removed with all it holds
+// when it's dropped, so whether the job succeeds, fails or panics.
+pub struct Workspace {
+    path: PathBuf,
+}
+
+impl Workspace {
+    pub fn create(root: &Path) -> Result<Self, String> {
+        let path = root.join(Uuid::new_v4().to_string());
+        fs::create_dir_all(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(Workspace { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn join(&self, name: &str) -> PathBuf {
+        self.path.join(name)
+    }
+}
+
+impl Drop for Workspace {
+    fn drop(&mut self) {
+        fs::remove_dir_all(&self.path).ok();
+    }
+}
+
+// where the workspaces are created.
+pub fn workspace_root() -> PathBuf {
+    let folder = match CACHE_DIR.get() {
+        Some(path) => PathBuf::from(path.as_str()),
+        None => std::env::temp_dir(),
+    };
+    folder.join(WORKSPACE_FOLDER)
+}
+
+// remove the workspaces not touched for `max_age`, returns how many were removed.
+pub fn sweep(root: &Path, max_age: Duration) -> usize {
+    let entries = match fs::read_dir(root) {
+        Ok(entries) => entries,
+        Err(_) => {
+            return 0;
+        }
+    };
+    let now = SystemTime::now();
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        // only the folders named like a workspace, in case the root was pointed somewhere else
+        let is_workspace = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| Uuid::parse_str(name).is_ok());
+        if !is_workspace || !path.is_dir() {
+            continue;
+        }
+        let age = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .map(|modified| now.duration_since(modified).unwrap_or_default())
+            .unwrap_or_default();
+        if age >= max_age && fs::remove_dir_all(&path).is_ok() {
+            removed += 1;
+        }
+    }
+    removed
+}
+
+// move a finished file where it belongs, by copying it when it's on another drive.
+pub fn move_file(from: &Path, to: &Path) -> Result<(), String> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to).map_err(|e| format!("{}: {}", to.display(), e))?;
+    fs::remove_file(from).ok();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::store::TestDir;
+    use super::*;
+
+    #[test]
+    fn test_workspace() {
+        let root = TestDir::new("workspace");
+        let workspace = Workspace::create(&root).unwrap();
+        let path = workspace.path().to_path_buf();
+        fs::write(workspace.join("book.htmlz"), "dump").unwrap();
+        fs::create_dir(workspace.join("html")).unwrap();
+        assert!(path.is_dir());
+
+        drop(workspace);
+        assert!(!path.exists());
+
+        // a panicking job still cleans up
+        let result = std::panic::catch_unwind(|| {
+            let workspace = Workspace::create(&root).unwrap();
+            fs::write(workspace.join("index1.html"), "<html></html>").unwrap();
+            panic!("job failed");
+        });
+        assert!(result.is_err());
+        assert_eq!(fs::read_dir(&root).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_sweep() {
+        let root = TestDir::new("sweep");
+        let orphan = root.join(Uuid::new_v4().to_string());
+        fs::create_dir_all(orphan.join("html")).unwrap();
+        fs::create_dir_all(root.join("not-a-workspace")).unwrap();
+
+        assert_eq!(sweep(&root, ORPHAN_AGE), 0);
+        assert!(orphan.exists());
+        assert_eq!(sweep(&root, Duration::ZERO), 1);
+        assert!(!orphan.exists());
+        assert!(root.join("not-a-workspace").exists());
+        assert_eq!(sweep(&root.join("missing"), Duration::ZERO), 0);
+    }
+
+    #[test]
+    fn test_move_file() {
+        let root = TestDir::new("move");
+        fs::create_dir_all(&root).unwrap();
+        let from = root.join("book.out.html");
+        let to = root.join("book.html");
+        fs::write(&from, "new").unwrap();
+        fs::write(&to, "old").unwrap();
+        move_file(&from, &to).unwrap();
+        assert_eq!(fs::read_to_string(&to).unwrap(), "new");
+        assert!(!from.exists());
+    }
+}
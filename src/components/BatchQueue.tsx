@@ -0,0 +1,112 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/tauri";
+import { listen } from "@tauri-apps/api/event";
+
+export default function BatchQueue({ batchId, onClose }: BatchQueueProps) {
+  const [books, setBooks] = useState<Map<string, BatchBookEvent>>(new Map());
+  const [progress, setProgress] = useState({ finished: 0, total: 0 });
+  const [summary, setSummary] = useState<BatchSummary | null>(null);
+
+  useEffect(() => {
+    const unlisten = [
+      listen<BatchBookEvent>("event-batch-book", (event) => {
+        const book = event.payload;
+        if (book.batch_id != batchId) {
+          return;
+        }
+        setBooks((books) => new Map(books).set(book.book, book));
+        setProgress((progress) => ({
+          finished: Math.max(progress.finished, book.finished),
+          total: book.total,
+        }));
+      }),
+      listen<BatchSummary>("event-batch", (event) => {
+        if (event.payload.batch_id == batchId) {
+          setSummary(event.payload);
+        }
+      }),
+    ];
+    return () => {
+      unlisten.forEach((promise) => promise.then((unlisten) => unlisten()));
+    };
+  }, [batchId]);
+
+  const running = [...books.values()].filter(
+    (book) => book.status == "started"
+  );
+  return (
+    <div className="menu-bg border border-gray-200 rounded-lg shadow dark:menu-bg dark:border-gray-700 p-4 text-sm font-normal text-gray-700 dark:text-gray-400">
+      <div className="flex flex-row justify-between mb-2">
+        <span className="font-medium text-gray-900 dark:text-white">
+          {summary
+            ? `${summary.succeeded.length} annotated, ${summary.failed.length} failed` +
+              (summary.cancelled.length > 0
+                ? `, ${summary.cancelled.length} cancelled`
+                : "")
+            : `${progress.finished}/${progress.total} books`}
+        </span>
+        <span className="space-x-3">
+          {!summary && (
+            <button
+              type="button"
+              onClick={() => invoke("cancel_job", { jobId: batchId })}
+            >
+              Cancel
+            </button>
+          )}
+          <button type="button" onClick={onClose} disabled={!summary}>
+            Close
+          </button>
+        </span>
+      </div>
+      {!summary &&
+        running.map((book) => (
+          <div key={book.book} className="line-clamp-1" title={book.book}>
+            Annotating {book.book}
+          </div>
+        ))}
+      {summary && (
+        <div className="max-h-40 overflow-y-auto space-y-1">
+          {summary.failed.map((book) => (
+            <div key={book.book} className="text-red-800 dark:text-red-300">
+              {book.book}: {book.error}
+            </div>
+          ))}
+          {summary.succeeded.map((book) => (
+            <div key={book.book} className="line-clamp-1" title={book.output}>
+              {book.output}
+            </div>
+          ))}
+        </div>
+      )}
+    </div>
+  );
+}
+
+export interface BatchBookEvent {
+  batch_id: string;
+  book: string;
+  status: "started" | "done" | "failed" | "cancelled";
+  finished: number;
+  total: number;
+  output: string | null;
+  error: string | null;
+}
+
+export interface BookOutcome {
+  book: string;
+  output?: string;
+  error?: string;
+}
+
+export interface BatchSummary {
+  batch_id: string;
+  succeeded: BookOutcome[];
+  failed: BookOutcome[];
+  cancelled: string[];
+}
+
+export interface BatchQueueProps {
+  batchId: string;
+  onClose: () => void;
+}
@@ -0,0 +1,77 @@
+import { dialog } from "@tauri-apps/api";
+import { invoke } from "@tauri-apps/api/tauri";
+
+export default function BookReport({ report, onClose }: BookReportProps) {
+  async function export_report() {
+    const path = await dialog.save({
+      filters: [
+        { name: "HTML", extensions: ["html"] },
+        { name: "CSV", extensions: ["csv"] },
+      ],
+    });
+    if (path != null) {
+      await invoke("export_book_report", { report: report, path: path });
+    }
+  }
+
+  return (
+    <div className="menu-bg border border-gray-200 rounded-lg shadow dark:menu-bg dark:border-gray-700 p-4 text-sm font-normal text-gray-700 dark:text-gray-400">
+      <div className="flex flex-row justify-between mb-2">
+        <span className="font-medium text-gray-900 dark:text-white">
+          {report.total_words} words, {report.unique_lemmas} lemmas, CEFR{" "}
+          {report.cefr_level}
+          {report.readability != null &&
+            `, readability ${report.readability.toFixed(1)}`}
+        </span>
+        <span className="space-x-3">
+          <button type="button" onClick={export_report}>
+            Export...
+          </button>
+          <button type="button" onClick={onClose}>
+            Close
+          </button>
+        </span>
+      </div>
+      <div>
+        Coverage:{" "}
+        {report.coverage
+          .map((level) => `level ${level.hint_lvl} ${level.percent.toFixed(1)}%`)
+          .join(", ")}
+        , not in dictionary {report.unlisted.percent.toFixed(1)}%
+      </div>
+      <div className="line-clamp-2">
+        Hard words:{" "}
+        {report.hard_words
+          .map((word) => `${word.lemma} (${word.count})`)
+          .join(", ")}
+      </div>
+    </div>
+  );
+}
+
+export interface LevelCoverage {
+  hint_lvl: number;
+  words: number;
+  percent: number;
+}
+
+export interface WordCount {
+  lemma: string;
+  hint_lvl: number;
+  count: number;
+}
+
+export interface BookReportData {
+  total_words: number;
+  unique_lemmas: number;
+  coverage: LevelCoverage[];
+  unlisted: LevelCoverage;
+  hard_words: WordCount[];
+  cefr_level: string;
+  readability: number | null;
+}
+
+export interface BookReportProps {
+  report: BookReportData;
+  onClose: () => void;
+}
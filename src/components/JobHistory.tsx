@@ -0,0 +1,99 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/tauri";
+
+export default function JobHistory({ onRerun, onClose }: JobHistoryProps) {
+  const [entries, setEntries] = useState<HistoryEntry[]>([]);
+  const [book, setBook] = useState("");
+  const [status, setStatus] = useState("");
+  const [error, setError] = useState("");
+
+  async function list_history() {
+    await invoke<HistoryEntry[]>("list_history", {
+      filter: { book: book, status: status || null },
+    })
+      .then(setEntries)
+      .catch(setError);
+  }
+
+  useEffect(() => {
+    list_history();
+  }, [book, status]);
+
+  async function delete_entry(id: string) {
+    await invoke("delete_history", { ids: [id] }).catch(setError);
+    list_history();
+  }
+
+  async function rerun(id: string) {
+    await invoke<string>("rerun_job", { id: id })
+      .then((jobId) => {
+        onRerun(jobId);
+        onClose();
+      })
+      .catch(setError);
+  }
+
+  return (
+    <div className="menu-bg border border-gray-200 rounded-lg shadow dark:menu-bg dark:border-gray-700 p-4 text-sm font-normal text-gray-700 dark:text-gray-400">
+      <div className="flex flex-row justify-between mb-2 gap-x-3">
+        <input
+          type="text"
+          value={book}
+          onChange={(e) => setBook(e.target.value)}
+          placeholder="filter by book..."
+          className="menu-bg border border-gray-300 rounded-lg flex-1 p-1 dark:border-gray-600"
+        />
+        <select
+          value={status}
+          onChange={(e) => setStatus(e.target.value)}
+          className="menu-bg border border-gray-300 rounded-lg p-1 dark:border-gray-600"
+        >
+          <option value="">All</option>
+          <option value="done">Done</option>
+          <option value="failed">Failed</option>
+          <option value="cancelled">Cancelled</option>
+        </select>
+        <button type="button" onClick={onClose}>
+          Close
+        </button>
+      </div>
+      {error && <div className="text-red-800 dark:text-red-300">{error}</div>}
+      <div className="max-h-48 overflow-y-auto space-y-1">
+        {entries.map((entry) => (
+          <div key={entry.id} className="flex flex-row justify-between gap-x-3">
+            <span className="line-clamp-1" title={entry.message}>
+              {new Date(entry.started * 1000).toLocaleString()} {entry.status}{" "}
+              {entry.book} ({Math.round(entry.duration_ms / 1000)}s)
+            </span>
+            <span className="space-x-3 whitespace-nowrap">
+              <button type="button" onClick={() => rerun(entry.id)}>
+                Re-run
+              </button>
+              <button type="button" onClick={() => delete_entry(entry.id)}>
+                Delete
+              </button>
+            </span>
+          </div>
+        ))}
+      </div>
+    </div>
+  );
+}
+
+export interface HistoryEntry {
+  id: string;
+  started: number;
+  duration_ms: number;
+  book: string;
+  checksum: string | null;
+  payload: object;
+  resources: { file: string; checksum: string }[];
+  output: string | null;
+  status: "done" | "failed" | "cancelled";
+  message: string;
+}
+
+export interface JobHistoryProps {
+  onRerun: (jobId: string) => void;
+  onClose: () => void;
+}
@@ -0,0 +1,108 @@
+import { useState } from "react";
+
+export default function NameReview({
+  detected,
+  excludedNames,
+  includedNames,
+  onChange,
+  onClose,
+}: NameReviewProps) {
+  const [added, setAdded] = useState("");
+
+  // a detected name is left alone unless the user says it's an ordinary word
+  function toggle(name: string) {
+    const key = name.toLowerCase();
+    const included = includedNames.some((n) => n.toLowerCase() == key);
+    onChange(
+      excludedNames,
+      included
+        ? includedNames.filter((n) => n.toLowerCase() != key)
+        : [...includedNames, name]
+    );
+  }
+
+  function add_name() {
+    const name = added.trim();
+    if (name != "" && !excludedNames.includes(name)) {
+      onChange([...excludedNames, name], includedNames);
+    }
+    setAdded("");
+  }
+
+  return (
+    <div className="menu-bg border border-gray-200 rounded-lg shadow dark:menu-bg dark:border-gray-700 p-4 text-sm font-normal text-gray-700 dark:text-gray-400">
+      <div className="flex flex-row justify-between mb-2">
+        <span className="font-medium text-gray-900 dark:text-white">
+          Names ({detected.length} found, the checked ones are not annotated
+          when they're capitalised, it applies to the next run)
+        </span>
+        <button type="button" onClick={onClose}>
+          Close
+        </button>
+      </div>
+      <div className="flex flex-row flex-wrap gap-x-4 gap-y-1 max-h-32 overflow-y-auto">
+        {detected.map((name) => (
+          <label key={name.name} className="inline-flex items-center">
+            <input
+              type="checkbox"
+              checked={
+                !includedNames.some(
+                  (n) => n.toLowerCase() == name.name.toLowerCase()
+                )
+              }
+              onChange={() => toggle(name.name)}
+              className="me-1"
+            />
+            {name.name} ({name.capitalized + name.after_honorific})
+          </label>
+        ))}
+        {excludedNames.map((name) => (
+          <label key={name} className="inline-flex items-center">
+            <input
+              type="checkbox"
+              checked={true}
+              onChange={() =>
+                onChange(
+                  excludedNames.filter((n) => n != name),
+                  includedNames
+                )
+              }
+              className="me-1"
+            />
+            {name}
+          </label>
+        ))}
+      </div>
+      <div className="flex flex-row space-x-2 mt-2">
+        <input
+          type="text"
+          value={added}
+          onChange={(e) => setAdded(e.target.value)}
+          onKeyDown={(e) => e.key == "Enter" && add_name()}
+          placeholder="a name which wasn't found..."
+          className="menu-bg border border-gray-300 text-gray-900 text-sm rounded-lg block flex-1 p-1.5 dark:menu-bg dark:border-gray-600 dark:text-white"
+        />
+        <button type="button" onClick={add_name}>
+          Add
+        </button>
+      </div>
+    </div>
+  );
+}
+
+export interface DetectedName {
+  name: string;
+  capitalized: number;
+  lowercase: number;
+  after_honorific: number;
+}
+
+export interface NameReviewProps {
+  detected: DetectedName[];
+  // names left alone on top of the detected ones
+  excludedNames: string[];
+  // detected names which are ordinary words
+  includedNames: string[];
+  onChange: (excludedNames: string[], includedNames: string[]) => void;
+  onClose: () => void;
+}
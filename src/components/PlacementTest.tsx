@@ -0,0 +1,107 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/tauri";
+
+export default function PlacementTest({
+  payload,
+  onHintLevel,
+  onClose,
+}: PlacementTestProps) {
+  const [questions, setQuestions] = useState<PlacementQuestion[]>([]);
+  const [answers, setAnswers] = useState<PlacementAnswer[]>([]);
+  const [result, setResult] = useState<PlacementResult | null>(null);
+  const [error, setError] = useState("");
+
+  useEffect(() => {
+    invoke<PlacementQuestion[]>("start_placement_test", { payload: payload })
+      .then(setQuestions)
+      .catch(setError);
+  }, []);
+
+  async function answer(known: boolean) {
+    const next = [...answers, { word: questions[answers.length].word, known }];
+    setAnswers(next);
+    if (next.length == questions.length) {
+      await invoke<PlacementResult>("finish_placement_test", { answers: next })
+        .then(setResult)
+        .catch(setError);
+    }
+  }
+
+  const question = questions[answers.length];
+  return (
+    <div className="menu-bg border border-gray-200 rounded-lg shadow dark:menu-bg dark:border-gray-700 p-4 text-sm font-normal text-gray-700 dark:text-gray-400">
+      <div className="flex flex-row justify-between mb-2">
+        <span className="font-medium text-gray-900 dark:text-white">
+          Placement test
+        </span>
+        <button type="button" onClick={onClose}>
+          Close
+        </button>
+      </div>
+      {error && <div className="text-red-800 dark:text-red-300">{error}</div>}
+      {!result && question && (
+        <div className="space-y-2">
+          <div>
+            Do you know this word? ({answers.length + 1}/{questions.length})
+          </div>
+          <div className="text-lg font-medium text-gray-900 dark:text-white">
+            {question.word}
+          </div>
+          <div className="space-x-3">
+            <button type="button" onClick={() => answer(true)}>
+              Yes
+            </button>
+            <button type="button" onClick={() => answer(false)}>
+              No
+            </button>
+          </div>
+        </div>
+      )}
+      {result && (
+        <div className="space-y-2">
+          <div>
+            You know about {result.vocabulary_size} words of the dictionary,
+            hint level {result.hint_level} is recommended.
+          </div>
+          <button
+            type="button"
+            onClick={() => {
+              onHintLevel(result.hint_level);
+              onClose();
+            }}
+          >
+            Use hint level {result.hint_level}
+          </button>
+        </div>
+      )}
+    </div>
+  );
+}
+
+export interface PlacementQuestion {
+  word: string;
+  hint_lvl: number;
+}
+
+export interface PlacementAnswer {
+  word: string;
+  known: boolean;
+}
+
+export interface PlacementResult {
+  vocabulary_size: number;
+  hint_level: number;
+  levels: {
+    hint_lvl: number;
+    headwords: number;
+    asked: number;
+    known: number;
+  }[];
+  known_words: string[];
+}
+
+export interface PlacementTestProps {
+  payload: object;
+  onHintLevel: (hintLevel: number) => void;
+  onClose: () => void;
+}
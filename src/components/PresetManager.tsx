@@ -0,0 +1,156 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/tauri";
+import { dialog } from "@tauri-apps/api";
+
+export default function PresetManager({
+  options,
+  onApply,
+  onClose,
+}: PresetManagerProps) {
+  const [presets, setPresets] = useState<Preset[]>([]);
+  const [name, setName] = useState("");
+  const [renaming, setRenaming] = useState<string | null>(null);
+  const [newName, setNewName] = useState("");
+  const [error, setError] = useState("");
+
+  async function list_presets() {
+    await invoke<Preset[]>("list_presets").then(setPresets).catch(setError);
+  }
+
+  useEffect(() => {
+    list_presets();
+  }, []);
+
+  // keep what happened, then show the presets as they are now
+  async function update(command: Promise<unknown>) {
+    setError("");
+    await command.catch(setError);
+    list_presets();
+  }
+
+  function create_preset() {
+    update(
+      invoke("create_preset", { preset: { name: name, options: options } }).then(
+        () => setName("")
+      )
+    );
+  }
+
+  function rename_preset(name: string) {
+    update(
+      invoke("rename_preset", { name: name, newName: newName }).then(() =>
+        setRenaming(null)
+      )
+    );
+  }
+
+  async function import_preset() {
+    const path = await dialog
+      .open({ filters: [{ name: "Preset", extensions: ["json"] }] })
+      .catch(() => null);
+    if (path != null) {
+      update(invoke("import_preset", { path: path.toString() }));
+    }
+  }
+
+  async function export_preset(name: string) {
+    const path = await dialog
+      .save({
+        defaultPath: `${name}.json`,
+        filters: [{ name: "Preset", extensions: ["json"] }],
+      })
+      .catch(() => null);
+    if (path != null) {
+      update(invoke("export_preset", { name: name, path: path }));
+    }
+  }
+
+  return (
+    <div className="menu-bg border border-gray-200 rounded-lg shadow dark:menu-bg dark:border-gray-700 p-4 text-sm font-normal text-gray-700 dark:text-gray-400">
+      <div className="flex flex-row justify-between mb-2 gap-x-3">
+        <input
+          type="text"
+          value={name}
+          onChange={(e) => setName(e.target.value)}
+          placeholder="name the options above..."
+          className="menu-bg border border-gray-300 rounded-lg flex-1 p-1 dark:border-gray-600"
+        />
+        <button type="button" onClick={create_preset} disabled={!name.trim()}>
+          Save
+        </button>
+        <button type="button" onClick={import_preset}>
+          Import...
+        </button>
+        <button type="button" onClick={onClose}>
+          Close
+        </button>
+      </div>
+      {error && <div className="text-red-800 dark:text-red-300">{error}</div>}
+      <div className="max-h-48 overflow-y-auto space-y-1">
+        {presets.map((preset) => (
+          <div key={preset.name} className="flex flex-row justify-between gap-x-3">
+            {renaming == preset.name ? (
+              <input
+                type="text"
+                value={newName}
+                onChange={(e) => setNewName(e.target.value)}
+                onKeyDown={(e) => e.key == "Enter" && rename_preset(preset.name)}
+                className="menu-bg border border-gray-300 rounded-lg flex-1 p-1 dark:border-gray-600"
+              />
+            ) : (
+              <span className="line-clamp-1">{preset.name}</span>
+            )}
+            <span className="space-x-3 whitespace-nowrap">
+              <button
+                type="button"
+                onClick={() => {
+                  onApply(preset);
+                  onClose();
+                }}
+              >
+                Apply
+              </button>
+              {renaming == preset.name ? (
+                <button type="button" onClick={() => rename_preset(preset.name)}>
+                  OK
+                </button>
+              ) : (
+                <button
+                  type="button"
+                  onClick={() => {
+                    setRenaming(preset.name);
+                    setNewName(preset.name);
+                  }}
+                >
+                  Rename
+                </button>
+              )}
+              <button type="button" onClick={() => export_preset(preset.name)}>
+                Export...
+              </button>
+              <button
+                type="button"
+                onClick={() =>
+                  update(invoke("delete_preset", { name: preset.name }))
+                }
+              >
+                Delete
+              </button>
+            </span>
+          </div>
+        ))}
+      </div>
+    </div>
+  );
+}
+
+export interface Preset {
+  name: string;
+  options: any;
+}
+
+export interface PresetManagerProps {
+  options: object;
+  onApply: (preset: Preset) => void;
+  onClose: () => void;
+}
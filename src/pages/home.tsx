@@ -1,4 +1,4 @@
-import { Fragment, useEffect, useState, useCallback } from "react";
+import { Fragment, useEffect, useRef, useState, useCallback } from "react";
 import { FontAwesomeIcon } from "@fortawesome/react-fontawesome";
 import { dialog } from "@tauri-apps/api";
 //import { appWindow } from "@tauri-apps/api/window";
@@ -6,11 +6,43 @@ import { invoke } from "@tauri-apps/api/tauri";
 import { listen } from "@tauri-apps/api/event";
 import SelectInput from "../components/selectInput";
 import Preview from "../components/Preview";
+import BookReport, { BookReportData } from "../components/BookReport";
+import PlacementTest from "../components/PlacementTest";
+import { AppSetting, GetSettings, SaveSettings } from "../utils/setting";
+import BatchQueue from "../components/BatchQueue";
+import JobHistory from "../components/JobHistory";
+import PresetManager, { Preset } from "../components/PresetManager";
+import NameReview, { DetectedName } from "../components/NameReview";
 import {
   faFolderOpen,
   faArrowsRotate,
 } from "@fortawesome/free-solid-svg-icons";
 
+interface JobResult {
+  job_id: string;
+  status: "done" | "failed" | "cancelled";
+  message: string;
+}
+
+const job_result_classes = {
+  done: "text-blue-800 dark:text-blue-300",
+  failed: "text-red-800 dark:text-red-300",
+  cancelled: "text-yellow-800 dark:text-yellow-300",
+};
+
+export interface CalibreReport {
+  path: string | null;
+  version: string | null;
+  // why calibre can't be used, null when it can
+  reason: string | null;
+  searched: string[];
+}
+
+interface StartupOptions {
+  preset: string | null;
+  books: string[];
+}
+
 class WorkMesg {
   class_name: string;
   text: string;
@@ -20,29 +52,116 @@ class WorkMesg {
   }
 }
 
+// the lines of the converter's output kept for the log
+const LOG_LINES = 200;
+
+function density_words(density: string): number {
+  const [, words] = density.split("-");
+  return words ? parseInt(words) : 0;
+}
+
+// "end hint level-end occurrences", the glosses fade towards them as the book goes on
+function progression(fade: string, series: string, seriesLength: number) {
+  if (fade == "") {
+    return null;
+  }
+  const [hintLevel, occurrences] = fade.split("-");
+  return {
+    end_hint_level: parseInt(hintLevel),
+    end_occurrences: occurrences ? parseInt(occurrences) : 0,
+    series_length: series ? seriesLength : 1,
+    series: series,
+  };
+}
+
 export default function Home() {
   async function check_ebook_convert() {
-    await invoke<boolean>("check_ebook_convert").then((result) => {
-      if (result) {
+    await invoke<CalibreReport>("check_ebook_convert").then((report) => {
+      if (report.reason == null) {
         setWorkMesg(
           new WorkMesg(
             "text-green-800 dark:text-green-300",
-            "Calibre detected, you're good to go!"
+            `Calibre ${report.version} detected, you're good to go!`
           )
         );
       } else {
+        setWorkMesg(new WorkMesg("text-red-800 dark:text-red-300", report.reason));
+      }
+    });
+  }
+
+  // start from the options saved as the defaults
+  function apply_defaults(settings: AppSetting) {
+    setSettings(settings);
+    apply_options(settings.defaults);
+  }
+
+  function apply_options(defaults: { [option: string]: any }) {
+    setFormat(defaults.format);
+    setLanguage(defaults.language);
+    setBookLanguage(defaults.book_language);
+    setWordwiseStyle(defaults.wordwise_style);
+    setOccurrenceLimit(
+      `${defaults.max_occurrences}${defaults.per_chapter ? "-chapter" : ""}`
+    );
+    setDensity(
+      `${defaults.max_density}${
+        defaults.density_words ? `-${defaults.density_words}` : ""
+      }`
+    );
+    setFrequencyTop(defaults.frequency_top);
+    setCefrLevel(defaults.cefr_level);
+    setHintLevel(defaults.hint_level);
+    setAllowLong(defaults.allow_long);
+    setShowPhoneme(defaults.show_phoneme);
+  }
+
+  async function save_defaults() {
+    if (settings == null) {
+      return;
+    }
+    await SaveSettings({ ...settings, defaults: preview_payload })
+      .then(() => {
+        setSettings({ ...settings, defaults: preview_payload });
         setWorkMesg(
-          new WorkMesg(
-            "text-red-800 dark:text-red-300",
-            "Calibre is not detected, please install calibre."
-          )
+          new WorkMesg("text-green-800 dark:text-green-300", "Defaults saved.")
         );
+      })
+      .catch((error) => {
+        setWorkMesg(new WorkMesg("text-red-800 dark:text-red-300", error));
+      });
+  }
+
+  // the preset and books given on the command line, the books are annotated right away
+  async function apply_startup_options(settings: AppSetting) {
+    const startup = await invoke<StartupOptions>("startup_options");
+    if (startup.preset != null) {
+      const presets = await invoke<Preset[]>("list_presets").catch(() => []);
+      const preset = presets.find((preset) => preset.name == startup.preset);
+      if (preset) {
+        setActivePreset(preset);
+        apply_options(preset.options);
       }
-    });
+    }
+    if (startup.books.length > 0) {
+      await invoke<string>("start_batch", {
+        payload: settings.defaults,
+        request: { paths: startup.books, recursive: true },
+        preset: startup.preset,
+      })
+        .then(setBatchId)
+        .catch((error) => {
+          setWorkMesg(new WorkMesg("text-red-800 dark:text-red-300", error));
+        });
+    }
   }
 
   useEffect(() => {
     check_ebook_convert();
+    GetSettings().then((settings) => {
+      apply_defaults(settings);
+      apply_startup_options(settings);
+    });
     notify("", "");
     if (window.__TAURI_METADATA__) {
       listen<number>("event-progress", (event) => {
@@ -50,6 +169,22 @@ export default function Home() {
       });
       listen<WorkMesg>("event-workmesg", (event) => {
         setWorkMesg(event.payload);
+        setLog((log) => [...log.slice(1 - LOG_LINES), event.payload.text]);
+      });
+      // the names found in the book, the user confirms or overrides them for the next run
+      listen<DetectedName[]>("event-names", (event) => {
+        setDetectedNames(event.payload);
+      });
+      listen<BookReportData>("event-book-report", (event) => {
+        setReport(event.payload);
+      });
+      listen<JobResult>("event-job", (event) => {
+        const result = event.payload;
+        setWorkMesg(
+          new WorkMesg(job_result_classes[result.status], result.message)
+        );
+        setJobId(null);
+        setWorking(false);
       });
     }
   }, []);
@@ -67,9 +202,29 @@ export default function Home() {
       case "language":
         preview_payload.language = value;
         break;
+      case "bookLanguage":
+        preview_payload.book_language = value;
+        break;
       case "wordwiseStyle":
         preview_payload.wordwise_style = value;
         break;
+      case "occurrenceLimit":
+        preview_payload.max_occurrences = parseInt(value);
+        preview_payload.per_chapter = value.endsWith("-chapter");
+        break;
+      case "cefrLevel":
+        preview_payload.cefr_level = value;
+        break;
+      case "frequencyTop":
+        preview_payload.frequency_top = value;
+        break;
+      case "density":
+        preview_payload.max_density = parseInt(value);
+        preview_payload.density_words = density_words(value);
+        break;
+      case "fade":
+        preview_payload.progression = progression(value, series, seriesLength);
+        break;
       case "hintLevel":
         preview_payload.hint_level = value;
         break;
@@ -83,12 +238,13 @@ export default function Home() {
 
     if (
       (preview_payload.format == "mobi" || preview_payload.format == "azw3") &&
-      preview_payload.wordwise_style == 1
+      preview_payload.wordwise_style == 1 &&
+      !preview_payload.conversion?.keep_ruby
     ) {
       setWorkMesg(
         new WorkMesg(
           "text-red-600 dark:text-red-500",
-          "Warning: Amazon Kindle probably does not support the `On top` style. "
+          "Amazon Kindle doesn't show the `On top` style, the book is annotated Inline unless the settings keep it. "
         )
       );
     } else {
@@ -98,10 +254,14 @@ export default function Home() {
     await invoke<string>("preview", {
       payload: preview_payload,
       original: default_preview,
-    }).then((res) => {
-      //console.log(res);
-      setPreview(res);
-    });
+    })
+      .then((res) => {
+        //console.log(res);
+        setPreview(res);
+      })
+      .catch((error) => {
+        setWorkMesg(new WorkMesg("text-red-800 dark:text-red-300", error));
+      });
   }
 
   function useNotifyingState<T>(
@@ -122,25 +282,68 @@ export default function Home() {
   }
 
   const [book, setbook] = useState("");
+  const [settings, setSettings] = useState<AppSetting | null>(null);
+  const [activePreset, setActivePreset] = useState<Preset | null>(null);
 
   const [format, setFormat] = useNotifyingState("epub", "format");
   const [language, setLanguage] = useNotifyingState("en", "language");
+  const [bookLanguage, setBookLanguage] = useNotifyingState(
+    "en",
+    "bookLanguage"
+  );
   const [wordwiseStyle, setWordwiseStyle] = useNotifyingState(
     0,
     "wordwiseStyle"
   );
+  const [occurrenceLimit, setOccurrenceLimit] = useNotifyingState(
+    "0",
+    "occurrenceLimit"
+  );
+  const [density, setDensity] = useNotifyingState("0", "density");
+  const [frequencyTop, setFrequencyTop] = useNotifyingState(0, "frequencyTop");
+  const [cefrLevel, setCefrLevel] = useNotifyingState<string | null>(
+    null,
+    "cefrLevel"
+  );
+  const [fade, setFade] = useNotifyingState("", "fade");
+  const [series, setSeries] = useState("");
+  const [seriesLength, setSeriesLength] = useState(3);
   const [hintLevel, setHintLevel] = useNotifyingState(3, "hintLevel");
   const [allowLong, setAllowLong] = useNotifyingState(false, "allowLong");
   const [showPhoneme, setShowPhoneme] = useNotifyingState(false, "showPhoneme");
+  const [detectedNames, setDetectedNames] = useState<DetectedName[]>([]);
+  const [excludedNames, setExcludedNames] = useState<string[]>([]);
+  const [includedNames, setIncludedNames] = useState<string[]>([]);
+  const [reviewingNames, setReviewingNames] = useState(false);
+
+  // the names belong to the book they were found in
+  useEffect(() => {
+    setDetectedNames([]);
+    setExcludedNames([]);
+    setIncludedNames([]);
+  }, [book]);
 
   let preview_payload = {
+    // the options not on this page, the annotation color for example
+    ...settings?.defaults,
+    ...activePreset?.options,
     book: book,
     format: format,
     language: language,
+    book_language: bookLanguage,
     hint_level: hintLevel,
     allow_long: allowLong,
     show_phoneme: showPhoneme,
     wordwise_style: wordwiseStyle,
+    max_occurrences: parseInt(occurrenceLimit),
+    per_chapter: occurrenceLimit.endsWith("-chapter"),
+    max_density: parseInt(density),
+    density_words: density_words(density),
+    frequency_top: frequencyTop,
+    cefr_level: cefrLevel,
+    progression: progression(fade, series, seriesLength),
+    excluded_names: excludedNames,
+    included_names: includedNames,
   };
 
   const default_preview: string =
@@ -149,34 +352,81 @@ export default function Home() {
   const [preview, setPreview] = useState(default_preview);
   const [progress, setProgress] = useState(0);
   const [working, setWorking] = useState(false);
+  const [jobId, setJobId] = useState<string | null>(null);
   const [selecting, setSelecting] = useState(false);
+  const [report, setReport] = useState<BookReportData | null>(null);
+  const [placing, setPlacing] = useState(false);
+  const [batchId, setBatchId] = useState<string | null>(null);
+  const [browsingHistory, setBrowsingHistory] = useState(false);
+  const [managingPresets, setManagingPresets] = useState(false);
   const [workmesg, setWorkMesg] = useState<WorkMesg>({
     class_name: " ",
     text: "",
   });
+  const [log, setLog] = useState<string[]>([]);
+  const logEnd = useRef<HTMLDivElement>(null);
+
+  // follow the newest line
+  useEffect(() => {
+    logEnd.current?.scrollIntoView({ block: "nearest" });
+  }, [log]);
 
   async function start_job() {
     setWorkMesg(new WorkMesg(" ", ""));
+    setLog([]);
     setWorking(true);
     await invoke<string>("start_job", {
       payload: {
+        ...settings?.defaults,
+        ...activePreset?.options,
         book: book,
         format: format,
         language: language,
+        book_language: bookLanguage,
         hint_level: hintLevel,
         allow_long: allowLong,
         show_phoneme: showPhoneme,
         wordwise_style: wordwiseStyle,
+        max_occurrences: parseInt(occurrenceLimit),
+        per_chapter: occurrenceLimit.endsWith("-chapter"),
+        max_density: parseInt(density),
+        density_words: density_words(density),
+        frequency_top: frequencyTop,
+        cefr_level: cefrLevel,
+        progression: progression(fade, series, seriesLength),
+        excluded_names: excludedNames,
+        included_names: includedNames,
       },
     })
-      .then((result) => {
-        setWorkMesg(new WorkMesg("text-blue-800 dark:text-blue-300", result));
-      })
+      // the job runs on, "event-job" tells when it's over
+      .then(setJobId)
       .catch((error) => {
         setWorkMesg(new WorkMesg("text-red-800 dark:text-red-300", error));
+        setWorking(false);
+      });
+  }
+
+  async function cancel_job() {
+    if (jobId != null) {
+      await invoke("cancel_job", { jobId: jobId }).catch((error) => {
+        setWorkMesg(new WorkMesg("text-red-800 dark:text-red-300", error));
       });
+    }
+  }
 
-    setWorking(false);
+  async function analyze_book() {
+    setWorkMesg(new WorkMesg(" ", ""));
+    setLog([]);
+    setWorking(true);
+    await invoke<string>("analyze_book", {
+      payload: preview_payload,
+    })
+      // the report comes with "event-book-report", "event-job" tells when it's over
+      .then(setJobId)
+      .catch((error) => {
+        setWorkMesg(new WorkMesg("text-red-800 dark:text-red-300", error));
+        setWorking(false);
+      });
   }
 
   async function select_book_dialog() {
@@ -196,6 +446,27 @@ export default function Home() {
     }
   }
 
+  // annotate every book of a folder and its subfolders with the settings above
+  async function start_batch() {
+    setSelecting(true);
+    const folder = await dialog
+      .open({ directory: true, multiple: false })
+      .catch(() => null);
+    setSelecting(false);
+    if (folder == null) {
+      return;
+    }
+    setWorkMesg(new WorkMesg(" ", ""));
+    await invoke<string>("start_batch", {
+      payload: preview_payload,
+      request: { paths: [folder.toString()], recursive: true },
+    })
+      .then(setBatchId)
+      .catch((error) => {
+        setWorkMesg(new WorkMesg("text-red-800 dark:text-red-300", error));
+      });
+  }
+
   const supported_languages = [
     { value: "en", text: "English" },
     { value: "cn", text: "中文" },
@@ -212,6 +483,17 @@ export default function Home() {
     { value: "th", text: "แบบไทย" },
     { value: "ua", text: "українська" },
   ];
+  const supported_book_languages = [
+    { value: "en", text: "English" },
+    { value: "de", text: "Deutsch" },
+    { value: "es", text: "Española" },
+    { value: "fr", text: "Français" },
+    { value: "pt", text: "Português" },
+    { value: "ru", text: "Русский" },
+    { value: "cn", text: "中文" },
+    { value: "jp", text: "日本語" },
+    { value: "th", text: "แบบไทย" },
+  ];
   const supported_formats = [
     { value: "epub", text: "epub" },
     { value: "mobi", text: "mobi" },
@@ -228,6 +510,42 @@ export default function Home() {
     { value: 0, text: "Inline" },
     { value: 1, text: "On top" },
   ];
+  const supported_occurrence_limits = [
+    { value: "0", text: "Every time" },
+    { value: "1", text: "First in book" },
+    { value: "3", text: "First 3 in book" },
+    { value: "1-chapter", text: "First in chapter" },
+    { value: "3-chapter", text: "First 3 in chapter" },
+  ];
+  const supported_difficulties = [
+    { value: 0, text: "Hint level" },
+    { value: 1000, text: "Outside top 1000" },
+    { value: 3000, text: "Outside top 3000" },
+    { value: 5000, text: "Outside top 5000" },
+    { value: 10000, text: "Outside top 10000" },
+    { value: 20000, text: "Outside top 20000" },
+  ];
+  const supported_cefr_levels = [
+    { value: "", text: "Not set" },
+    { value: "A1", text: "A1" },
+    { value: "A2", text: "A2" },
+    { value: "B1", text: "B1" },
+    { value: "B2", text: "B2" },
+    { value: "C1", text: "C1" },
+  ];
+  const supported_fades = [
+    { value: "", text: "Off" },
+    { value: "1", text: "To hint level 1" },
+    { value: "1-1", text: "To level 1, once" },
+  ];
+  // "annotations-words", without the words part the annotations are counted per paragraph
+  const supported_densities = [
+    { value: "0", text: "No limit" },
+    { value: "1", text: "1 per paragraph" },
+    { value: "3", text: "3 per paragraph" },
+    { value: "1-20", text: "1 per 20 words" },
+    { value: "1-50", text: "1 per 50 words" },
+  ];
 
   const select_options = [
     {
@@ -238,6 +556,14 @@ export default function Home() {
       onChange: (e: React.ChangeEvent<HTMLSelectElement>) =>
         setFormat(e.target.value),
     },
+    {
+      id: "book-language-select",
+      label: "Book Language",
+      value: bookLanguage,
+      options: supported_book_languages,
+      onChange: (e: React.ChangeEvent<HTMLSelectElement>) =>
+        setBookLanguage(e.target.value),
+    },
     {
       id: "language-select",
       label: "Wordwise Language",
@@ -254,6 +580,46 @@ export default function Home() {
       onChange: (e: React.ChangeEvent<HTMLSelectElement>) =>
         setWordwiseStyle(parseInt(e.target.value)),
     },
+    {
+      id: "occurrence-select",
+      label: "Annotate",
+      value: occurrenceLimit,
+      options: supported_occurrence_limits,
+      onChange: (e: React.ChangeEvent<HTMLSelectElement>) =>
+        setOccurrenceLimit(e.target.value),
+    },
+    {
+      id: "difficulty-select",
+      label: "Annotate Words",
+      value: frequencyTop,
+      options: supported_difficulties,
+      onChange: (e: React.ChangeEvent<HTMLSelectElement>) =>
+        setFrequencyTop(parseInt(e.target.value)),
+    },
+    {
+      id: "cefr-select",
+      label: "Reader Level",
+      value: cefrLevel ?? "",
+      options: supported_cefr_levels,
+      onChange: (e: React.ChangeEvent<HTMLSelectElement>) =>
+        setCefrLevel(e.target.value || null),
+    },
+    {
+      id: "density-select",
+      label: "Density",
+      value: density,
+      options: supported_densities,
+      onChange: (e: React.ChangeEvent<HTMLSelectElement>) =>
+        setDensity(e.target.value),
+    },
+    {
+      id: "fade-select",
+      label: "Fade",
+      value: fade,
+      options: supported_fades,
+      onChange: (e: React.ChangeEvent<HTMLSelectElement>) =>
+        setFade(e.target.value),
+    },
   ];
 
   return (
@@ -305,6 +671,30 @@ export default function Home() {
             />
           ))}
         </div>
+        {fade != "" && (
+          <div className="flex flex-row gap-x-5 text-sm font-medium text-gray-900 dark:text-white">
+            <label className="flex-1">
+              Series (books of a series continue from each other, leave empty
+              for a single book)
+              <input
+                type="text"
+                value={series}
+                onChange={(e) => setSeries(e.target.value)}
+                className="menu-bg border border-gray-300 text-gray-900 text-sm rounded-lg block w-full p-2.5 dark:menu-bg dark:border-gray-600 dark:text-white"
+              />
+            </label>
+            <label>
+              Books
+              <input
+                type="number"
+                min={1}
+                value={seriesLength}
+                onChange={(e) => setSeriesLength(parseInt(e.target.value) || 1)}
+                className="menu-bg border border-gray-300 text-gray-900 text-sm rounded-lg block w-24 p-2.5 dark:menu-bg dark:border-gray-600 dark:text-white"
+              />
+            </label>
+          </div>
+        )}
         <div>
           <label
             htmlFor="minmax-range"
@@ -385,6 +775,90 @@ export default function Home() {
             }
             Process
           </button>
+          {jobId != null && (
+            <button
+              type="button"
+              onClick={cancel_job}
+              className="text-gray-900 bg-white border border-gray-300 hover:bg-gray-100
+              font-medium rounded-lg text-sm px-5 py-2.5 text-center inline-flex
+             items-center dark:bg-gray-800 dark:text-white dark:border-gray-600 dark:hover:bg-gray-700"
+            >
+              Cancel
+            </button>
+          )}
+          <button
+            type="button"
+            onClick={start_batch}
+            disabled={working || selecting || batchId != null}
+            className="disabled:opacity-50 text-gray-900 bg-white border border-gray-300 hover:bg-gray-100
+              font-medium rounded-lg text-sm px-5 py-2.5 text-center inline-flex
+             items-center dark:bg-gray-800 dark:text-white dark:border-gray-600 dark:hover:bg-gray-700"
+          >
+            Folder...
+          </button>
+          <button
+            type="button"
+            onClick={analyze_book}
+            disabled={working || selecting}
+            className="disabled:opacity-50 text-gray-900 bg-white border border-gray-300 hover:bg-gray-100
+              font-medium rounded-lg text-sm px-5 py-2.5 text-center inline-flex
+             items-center dark:bg-gray-800 dark:text-white dark:border-gray-600 dark:hover:bg-gray-700"
+          >
+            Analyze
+          </button>
+          <button
+            type="button"
+            onClick={() => setPlacing(true)}
+            disabled={working || placing}
+            className="disabled:opacity-50 text-gray-900 bg-white border border-gray-300 hover:bg-gray-100
+              font-medium rounded-lg text-sm px-5 py-2.5 text-center inline-flex
+             items-center dark:bg-gray-800 dark:text-white dark:border-gray-600 dark:hover:bg-gray-700"
+          >
+            Test My Level
+          </button>
+          {detectedNames.length > 0 && (
+            <button
+              type="button"
+              onClick={() => setReviewingNames(true)}
+              disabled={reviewingNames}
+              className="disabled:opacity-50 text-gray-900 bg-white border border-gray-300 hover:bg-gray-100
+              font-medium rounded-lg text-sm px-5 py-2.5 text-center inline-flex
+             items-center dark:bg-gray-800 dark:text-white dark:border-gray-600 dark:hover:bg-gray-700"
+            >
+              Names
+            </button>
+          )}
+          <button
+            type="button"
+            onClick={() => setBrowsingHistory(true)}
+            disabled={browsingHistory}
+            className="disabled:opacity-50 text-gray-900 bg-white border border-gray-300 hover:bg-gray-100
+              font-medium rounded-lg text-sm px-5 py-2.5 text-center inline-flex
+             items-center dark:bg-gray-800 dark:text-white dark:border-gray-600 dark:hover:bg-gray-700"
+          >
+            History
+          </button>
+          <button
+            type="button"
+            onClick={() => setManagingPresets(true)}
+            disabled={managingPresets}
+            title={activePreset ? `Applied: ${activePreset.name}` : ""}
+            className="disabled:opacity-50 text-gray-900 bg-white border border-gray-300 hover:bg-gray-100
+              font-medium rounded-lg text-sm px-5 py-2.5 text-center inline-flex
+             items-center dark:bg-gray-800 dark:text-white dark:border-gray-600 dark:hover:bg-gray-700"
+          >
+            Presets
+          </button>
+          <button
+            type="button"
+            onClick={save_defaults}
+            disabled={settings == null}
+            className="disabled:opacity-50 text-gray-900 bg-white border border-gray-300 hover:bg-gray-100
+              font-medium rounded-lg text-sm px-5 py-2.5 text-center inline-flex
+             items-center dark:bg-gray-800 dark:text-white dark:border-gray-600 dark:hover:bg-gray-700"
+          >
+            Save as Defaults
+          </button>
           <div className="flex items-center">
             <div
               id="message"
@@ -402,9 +876,60 @@ export default function Home() {
               style={{ width: `${progress}%` }}
             ></div>
           </div>
+          {working && log.length > 0 && (
+            <div className="max-h-24 overflow-y-auto mb-4 font-mono text-xs text-gray-600 dark:text-gray-400">
+              {log.map((line, index) => (
+                <div key={index} className="whitespace-pre-wrap">
+                  {line}
+                </div>
+              ))}
+              <div ref={logEnd} />
+            </div>
+          )}
         </div>
         <div>
-          <Preview innerHTML={preview} />
+          {managingPresets ? (
+            <PresetManager
+              options={preview_payload}
+              onApply={(preset) => {
+                setActivePreset(preset);
+                apply_options(preset.options);
+              }}
+              onClose={() => setManagingPresets(false)}
+            />
+          ) : browsingHistory ? (
+            <JobHistory
+              onRerun={(jobId) => {
+                setWorkMesg(new WorkMesg(" ", ""));
+                setWorking(true);
+                setJobId(jobId);
+              }}
+              onClose={() => setBrowsingHistory(false)}
+            />
+          ) : batchId != null ? (
+            <BatchQueue batchId={batchId} onClose={() => setBatchId(null)} />
+          ) : reviewingNames ? (
+            <NameReview
+              detected={detectedNames}
+              excludedNames={excludedNames}
+              includedNames={includedNames}
+              onChange={(excluded, included) => {
+                setExcludedNames(excluded);
+                setIncludedNames(included);
+              }}
+              onClose={() => setReviewingNames(false)}
+            />
+          ) : placing ? (
+            <PlacementTest
+              payload={preview_payload}
+              onHintLevel={setHintLevel}
+              onClose={() => setPlacing(false)}
+            />
+          ) : report ? (
+            <BookReport report={report} onClose={() => setReport(null)} />
+          ) : (
+            <Preview innerHTML={preview} />
+          )}
         </div>
       </div>
     </Fragment>
@@ -1,4 +1,7 @@
-import { useState } from "react";
+import { useEffect, useState } from "react";
+import { dialog } from "@tauri-apps/api";
+import { invoke } from "@tauri-apps/api/tauri";
+import { CalibreReport } from "./home";
 import { AppSetting, GetSettings, SaveSettings } from "../utils/setting";
 
 function setTheme(theme: string) {
@@ -8,17 +11,111 @@ function setTheme(theme: string) {
     document.documentElement.classList.remove("dark");
   }
 
-  const settings: AppSetting = {
-    theme: theme,
-  };
+  // the other settings stay as they are
+  GetSettings()
+    .then((settings) => SaveSettings({ ...settings, theme: theme }))
+    .then((res) => {
+      console.log(res);
+    });
+}
+
+function setAnnotationColor(color: string) {
+  GetSettings().then((settings: AppSetting) =>
+    SaveSettings({
+      ...settings,
+      defaults: { ...settings.defaults, annotation_color: color },
+    }).catch((error) => console.error(error))
+  );
+}
 
-  SaveSettings(settings).then((res) => {
-    console.log(res);
+// where the annotated books go and how they are named, an error tells what the settings can't take
+async function setOutput(
+  change: (settings: AppSetting) => AppSetting
+): Promise<AppSetting> {
+  return GetSettings().then(async (settings) => {
+    const changed = change(settings);
+    await SaveSettings(changed);
+    return changed;
   });
 }
 
+interface ConversionOptions {
+  output_profile?: string | null;
+  epub_version?: number | null;
+  mobi_file_type?: string | null;
+  paper_size?: string | null;
+  pdf_default_font_size?: number | null;
+  extra_args?: string[];
+  keep_ruby?: boolean;
+}
+
 function SettingsPage() {
   const [setting_text, set_setting_text] = useState("");
+  const [outputDir, setOutputDir] = useState("");
+  const [outputName, setOutputName] = useState("");
+  const [overwrite, setOverwrite] = useState("overwrite");
+  const [outputError, setOutputError] = useState("");
+  const [ebookConvert, setEbookConvert] = useState("");
+  const [calibre, setCalibre] = useState<CalibreReport | null>(null);
+  const [conversion, setConversion] = useState<ConversionOptions>({});
+  const [extraArgs, setExtraArgs] = useState("");
+
+  useEffect(() => {
+    GetSettings().then((settings) => {
+      setOutputDir(settings.output_dir);
+      setOutputName(settings.defaults.output_name);
+      setOverwrite(settings.defaults.overwrite);
+      setEbookConvert(settings.ebook_convert);
+      setConversion(settings.defaults.conversion ?? {});
+      setExtraArgs((settings.defaults.conversion?.extra_args ?? []).join(" "));
+    });
+    check_ebook_convert();
+  }, []);
+
+  // an empty field leaves calibre's default
+  function set_conversion(change: ConversionOptions) {
+    const changed = { ...conversion, ...change };
+    setConversion(changed);
+    save_output((settings) => ({
+      ...settings,
+      defaults: { ...settings.defaults, conversion: changed },
+    }));
+  }
+
+  async function check_ebook_convert() {
+    await invoke<CalibreReport>("check_ebook_convert").then(setCalibre);
+  }
+
+  async function set_ebook_convert(path: string) {
+    setEbookConvert(path);
+    await setOutput((settings) => ({ ...settings, ebook_convert: path }))
+      .then(check_ebook_convert)
+      .catch(setOutputError);
+  }
+
+  async function select_ebook_convert() {
+    const path = await dialog
+      .open({ directory: false, multiple: false })
+      .catch(() => null);
+    if (path != null) {
+      set_ebook_convert(path.toString());
+    }
+  }
+
+  function save_output(change: (settings: AppSetting) => AppSetting) {
+    setOutputError("");
+    setOutput(change).catch(setOutputError);
+  }
+
+  async function select_output_dir() {
+    const folder = await dialog
+      .open({ directory: true, multiple: false })
+      .catch(() => null);
+    if (folder != null) {
+      setOutputDir(folder.toString());
+      save_output((settings) => ({ ...settings, output_dir: folder.toString() }));
+    }
+  }
   async function read_settings() {
     GetSettings().then((res) => {
       set_setting_text(JSON.stringify(res));
@@ -55,6 +152,204 @@ function SettingsPage() {
       >
         Read Settings
       </button>
+      <div className="mt-4 text-black dark:text-white">
+        <label htmlFor="annotation-color">
+          Annotation color of the color style{" "}
+        </label>
+        <input
+          id="annotation-color"
+          type="color"
+          defaultValue="#ff0000"
+          onChange={(e) => setAnnotationColor(e.target.value)}
+        />
+      </div>
+      <div className="mt-4 text-black dark:text-white">
+        <label htmlFor="output-dir">Save the annotated books to </label>
+        <input
+          id="output-dir"
+          type="text"
+          readOnly
+          value={outputDir}
+          placeholder="the book's folder"
+          className="border border-gray-300 rounded p-1 text-black"
+        />
+        <button
+          onClick={select_output_dir}
+          className="btn px-3 py-1 rounded text-black bg-gray-300 dark:bg-slate-700/75 dark:text-white hover:bg-emerald-700 hover:text-white"
+        >
+          Choose...
+        </button>
+        <button
+          onClick={() => {
+            setOutputDir("");
+            save_output((settings) => ({ ...settings, output_dir: "" }));
+          }}
+          className="btn px-3 py-1 rounded text-black bg-gray-300 dark:bg-slate-700/75 dark:text-white hover:bg-emerald-700 hover:text-white"
+        >
+          Book's Folder
+        </button>
+      </div>
+      <div className="mt-4 text-black dark:text-white">
+        <label htmlFor="output-name">
+          Name them after {"{stem} {ext} {lang} {book_lang} {level}"}{" "}
+        </label>
+        <input
+          id="output-name"
+          type="text"
+          value={outputName}
+          onChange={(e) => setOutputName(e.target.value)}
+          onBlur={() =>
+            save_output((settings) => ({
+              ...settings,
+              defaults: { ...settings.defaults, output_name: outputName },
+            }))
+          }
+          className="border border-gray-300 rounded p-1 text-black"
+        />
+      </div>
+      <div className="mt-4 text-black dark:text-white">
+        <label htmlFor="overwrite">When the annotated book is already there </label>
+        <select
+          id="overwrite"
+          value={overwrite}
+          onChange={(e) => {
+            const policy = e.target.value;
+            setOverwrite(policy);
+            save_output((settings) => ({
+              ...settings,
+              defaults: { ...settings.defaults, overwrite: policy },
+            }));
+          }}
+          className="border border-gray-300 rounded p-1 text-black"
+        >
+          <option value="overwrite">overwrite it</option>
+          <option value="skip">skip the book</option>
+          <option value="suffix">add a number to the name</option>
+        </select>
+      </div>
+      <div className="mt-4 text-black dark:text-white">
+        <label htmlFor="ebook-convert">Calibre's ebook-convert </label>
+        <input
+          id="ebook-convert"
+          type="text"
+          readOnly
+          value={ebookConvert}
+          placeholder="found automatically"
+          className="border border-gray-300 rounded p-1 text-black"
+        />
+        <button
+          onClick={select_ebook_convert}
+          className="btn px-3 py-1 rounded text-black bg-gray-300 dark:bg-slate-700/75 dark:text-white hover:bg-emerald-700 hover:text-white"
+        >
+          Choose...
+        </button>
+        <button
+          onClick={() => set_ebook_convert("")}
+          className="btn px-3 py-1 rounded text-black bg-gray-300 dark:bg-slate-700/75 dark:text-white hover:bg-emerald-700 hover:text-white"
+        >
+          Find It
+        </button>
+        {calibre && (
+          <p
+            title={calibre.searched.join("\n")}
+            className={calibre.reason ? "text-red-800 dark:text-red-300" : ""}
+          >
+            {calibre.reason ?? `calibre ${calibre.version} at ${calibre.path}`}
+          </p>
+        )}
+      </div>
+      <div className="mt-4 text-black dark:text-white space-y-2">
+        <div>Calibre's options for the annotated books</div>
+        <div>
+          <label htmlFor="output-profile">Output profile </label>
+          <input
+            id="output-profile"
+            type="text"
+            defaultValue={conversion.output_profile ?? ""}
+            key={conversion.output_profile ?? ""}
+            placeholder="kindle for mobi and azw3"
+            onBlur={(e) =>
+              set_conversion({ output_profile: e.target.value.trim() || null })
+            }
+            className="border border-gray-300 rounded p-1 text-black"
+          />
+        </div>
+        <div>
+          <label htmlFor="epub-version">EPUB version </label>
+          <select
+            id="epub-version"
+            value={conversion.epub_version ?? ""}
+            onChange={(e) =>
+              set_conversion({
+                epub_version: e.target.value ? parseInt(e.target.value) : null,
+              })
+            }
+            className="border border-gray-300 rounded p-1 text-black"
+          >
+            <option value="">calibre's default</option>
+            <option value="2">2</option>
+            <option value="3">3</option>
+          </select>
+          <label htmlFor="mobi-file-type"> MOBI file type </label>
+          <select
+            id="mobi-file-type"
+            value={conversion.mobi_file_type ?? ""}
+            onChange={(e) =>
+              set_conversion({ mobi_file_type: e.target.value || null })
+            }
+            className="border border-gray-300 rounded p-1 text-black"
+          >
+            <option value="">new</option>
+            <option value="old">old</option>
+            <option value="both">both</option>
+          </select>
+          <label htmlFor="paper-size"> PDF paper size </label>
+          <select
+            id="paper-size"
+            value={conversion.paper_size ?? ""}
+            onChange={(e) => set_conversion({ paper_size: e.target.value || null })}
+            className="border border-gray-300 rounded p-1 text-black"
+          >
+            <option value="">calibre's default</option>
+            {["a4", "a5", "a6", "b5", "b6", "letter", "legal"].map((size) => (
+              <option key={size} value={size}>
+                {size}
+              </option>
+            ))}
+          </select>
+        </div>
+        <div>
+          <label htmlFor="extra-args">Other options </label>
+          <input
+            id="extra-args"
+            type="text"
+            value={extraArgs}
+            placeholder="--embed-all-fonts --margin-top=10"
+            onChange={(e) => setExtraArgs(e.target.value)}
+            onBlur={() =>
+              set_conversion({
+                extra_args: extraArgs.split(/\s+/).filter((arg) => arg != ""),
+              })
+            }
+            className="border border-gray-300 rounded p-1 text-black"
+          />
+        </div>
+        <div>
+          <label>
+            <input
+              type="checkbox"
+              checked={conversion.keep_ruby ?? false}
+              onChange={(e) => set_conversion({ keep_ruby: e.target.checked })}
+              className="me-1"
+            />
+            Keep the "On top" style in MOBI and AZW3 books (most Kindles show
+            it in brackets, it's Inline otherwise)
+          </label>
+        </div>
+      </div>
+      {outputError && (
+        <p className="text-red-800 dark:text-red-300">{outputError}</p>
+      )}
       <p className="text-black dark:text-white">{setting_text}</p>
     </div>
   );
@@ -3,11 +3,15 @@ import { listen } from "@tauri-apps/api/event";
 
 // define an application class/struct for the settings
 // this will be used to store the settings
-export class AppSetting {
+export interface AppSetting {
+  version: number;
   theme: string;
-  constructor(theme: string) {
-    this.theme = theme;
-  }
+  // the options the main page starts with, a payload without a book
+  defaults: { [option: string]: any };
+  // where the annotated books are saved, empty saves them next to the book
+  output_dir: string;
+  // calibre's ebook-convert, empty looks for it
+  ebook_convert: string;
 }
 
 export async function GetSettings(): Promise<AppSetting> {